@@ -0,0 +1,82 @@
+use crate::errors::{Context, Operation, RncError};
+use filetime::FileTime;
+use log::warn;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replaces the contents of the existing file at `path` with whatever `write` writes, unless
+/// `write` returns `false` along with its result, leaving the file untouched.
+///
+/// The new contents are written to a temporary file in the same directory, synced to disk
+/// and renamed over the original, so the original is left intact if anything fails midway.
+/// Permissions of the original file are preserved, and so is its ownership, if the process
+/// is allowed to change it. Symbolic links are followed, replacing the file they point to.
+pub fn replace_file<T, F>(path: &Path, write: F) -> Result<T, RncError>
+where
+    F: FnOnce(&mut fs::File) -> Result<(T, bool), RncError>,
+{
+    // errors are reported with the path as given, which is what the user knows the file by
+    let target = fs::canonicalize(path).context_at(Operation::Read, path)?;
+    let metadata = fs::metadata(&target).context_at(Operation::Read, path)?;
+
+    let mut temp = tempfile::Builder::new()
+        .prefix(".rnc")
+        .tempfile_in(parent_dir(&target))
+        .context_at(Operation::Write, path)?;
+    let (result, replace) = write(temp.as_file_mut()).map_err(|e| e.in_file(path))?;
+    if !replace {
+        return Ok(result);
+    }
+    temp.as_file_mut()
+        .flush()
+        .and_then(|_| temp.as_file().sync_all())
+        .and_then(|_| fs::set_permissions(temp.path(), metadata.permissions()))
+        .context_at(Operation::Write, path)?;
+    if let Err(e) = copy_owner(&metadata, temp.as_file()) {
+        warn!("{}: Could not preserve ownership: {}", path.display(), e);
+    }
+    temp.persist(&target)
+        .map_err(|e| e.error)
+        .context_at(Operation::Rename, path)?;
+
+    Ok(result)
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(unix)]
+fn copy_owner(from: &fs::Metadata, to: &fs::File) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    let current = to.metadata()?;
+    if current.uid() == from.uid() && current.gid() == from.gid() {
+        return Ok(());
+    }
+    // SAFETY: the file descriptor is valid for as long as `to` is borrowed
+    if unsafe { libc::fchown(to.as_raw_fd(), from.uid(), from.gid()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_from: &fs::Metadata, _to: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+/// Sets access and modification times of the file at `path` to the ones in `from`.
+pub fn copy_times(from: &fs::Metadata, path: &Path) -> io::Result<()> {
+    filetime::set_file_times(
+        path,
+        FileTime::from_last_access_time(from),
+        FileTime::from_last_modification_time(from),
+    )
+}
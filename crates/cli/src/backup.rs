@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix used by `--backup` without a value.
+const DEFAULT_SUFFIX: &str = ".orig";
+
+/// How to name backups of the files being overwritten.
+pub enum Backup {
+    /// Append given suffix, overwriting any previous backup.
+    Simple(String),
+    /// Append `.~N~`, with `N` one greater than of any existing backup, like GNU `cp --backup=numbered`.
+    Numbered,
+}
+
+impl Backup {
+    /// Parses the value of `--backup`.
+    pub fn new(value: Option<&str>) -> Self {
+        match value {
+            Some("numbered") => Backup::Numbered,
+            Some(suffix) => Backup::Simple(suffix.to_owned()),
+            None => Backup::Simple(DEFAULT_SUFFIX.to_owned()),
+        }
+    }
+
+    /// Copies the file at `path` to its backup. Returns the path of the backup.
+    pub fn create(&self, path: &Path) -> io::Result<PathBuf> {
+        let backup = self.backup_path(path)?;
+        fs::copy(path, &backup)?;
+
+        Ok(backup)
+    }
+
+    fn backup_path(&self, path: &Path) -> io::Result<PathBuf> {
+        let mut name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?
+            .to_owned();
+        match self {
+            Backup::Simple(suffix) => name.push(suffix),
+            Backup::Numbered => name.push(format!(".~{}~", last_number(path)? + 1)),
+        }
+
+        Ok(path.with_file_name(name))
+    }
+}
+
+/// Finds the greatest number of existing numbered backups of `path`, or 0 if there are none.
+fn last_number(path: &Path) -> io::Result<u64> {
+    let prefix = format!(
+        "{}.~",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    let mut last = 0;
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if name.len() > prefix.len() + 1 && name.starts_with(&prefix) && name.ends_with('~') {
+            if let Ok(n) = name[prefix.len()..name.len() - 1].parse() {
+                last = last.max(n);
+            }
+        }
+    }
+
+    Ok(last)
+}
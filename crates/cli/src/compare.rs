@@ -0,0 +1,54 @@
+use std::io::{self, Read, Write};
+
+/// A writer passing everything written to it on to `inner`, comparing it with the contents
+/// of `original` on the way.
+pub struct CompareWriter<R: Read, W: Write> {
+    original: R,
+    inner: W,
+    buffer: Vec<u8>,
+    differs: bool,
+}
+
+impl<R: Read, W: Write> CompareWriter<R, W> {
+    pub fn new(original: R, inner: W) -> Self {
+        CompareWriter {
+            original,
+            inner,
+            buffer: Vec::new(),
+            differs: false,
+        }
+    }
+
+    /// Returns `true` if the written data differs from `original`.
+    pub fn finish(mut self) -> io::Result<bool> {
+        self.inner.flush()?;
+        if !self.differs {
+            // anything left in the original means the written data was shorter
+            self.differs = self.original.read(&mut [0])? != 0;
+        }
+
+        Ok(self.differs)
+    }
+}
+
+impl<R: Read, W: Write> Write for CompareWriter<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(buf)?;
+        if self.differs {
+            return Ok(buf.len());
+        }
+
+        self.buffer.resize(buf.len(), 0);
+        match self.original.read_exact(&mut self.buffer) {
+            Ok(()) => self.differs = self.buffer[..] != buf[..],
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => self.differs = true,
+            Err(e) => return Err(e),
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
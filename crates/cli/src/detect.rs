@@ -0,0 +1,135 @@
+use crate::transcode::is_wide;
+use crate::utf32::{UTF_32BE, UTF_32LE};
+use encoding::all::{UTF_16BE, UTF_16LE, UTF_8};
+use encoding::{DecoderTrap, EncodingRef};
+
+/// Encoding of the input, detected or given by the user.
+pub struct InputEncoding {
+    pub encoding: EncodingRef,
+    /// Length of the byte order mark at the beginning of the input.
+    pub bom_len: usize,
+    /// Whether nothing indicated the encoding, and UTF-8 is just assumed.
+    pub assumed: bool,
+    /// Whether the encoding was only guessed from the content, without a BOM.
+    pub guessed: bool,
+    /// Whether invalid input should be refused, instead of decoded with replacement characters.
+    pub strict: bool,
+}
+
+impl InputEncoding {
+    /// Detects encoding of the input by looking at its beginning, unless `explicit` is given.
+    pub fn detect(head: &[u8], explicit: Option<EncodingRef>) -> Self {
+        let bom = bom(head);
+        if let Some(explicit) = explicit {
+            return InputEncoding {
+                encoding: explicit,
+                // a BOM not matching the explicit encoding is just data
+                bom_len: match bom {
+                    Some((e, len)) if e.name() == explicit.name() => len,
+                    _ => 0,
+                },
+                assumed: false,
+                guessed: false,
+                strict: false,
+            };
+        }
+
+        if let Some((encoding, bom_len)) = bom {
+            InputEncoding {
+                encoding,
+                bom_len,
+                assumed: false,
+                guessed: false,
+                strict: false,
+            }
+        } else if let Some(encoding) = guess_utf16(head) {
+            InputEncoding {
+                encoding,
+                bom_len: 0,
+                assumed: false,
+                guessed: true,
+                strict: false,
+            }
+        } else {
+            InputEncoding {
+                encoding: UTF_8,
+                bom_len: 0,
+                assumed: true,
+                guessed: false,
+                strict: false,
+            }
+        }
+    }
+
+    /// Trap for decoding errors. If the encoding was just assumed or guessed, the input is only
+    /// decoded if it is valid, so it isn't corrupted with replacement characters if it is in
+    /// a different encoding.
+    pub fn decoder_trap(&self) -> DecoderTrap {
+        if self.assumed || self.guessed || self.strict {
+            DecoderTrap::Strict
+        } else {
+            DecoderTrap::Replace
+        }
+    }
+
+    /// Whether the input is converted as it is, without decoding it. Nothing indicated its
+    /// encoding, so it may be in some ASCII-compatible encoding other than UTF-8, in which line
+    /// endings are the same bytes. Such input is refused only if it has to be valid.
+    pub fn is_raw(&self) -> bool {
+        self.assumed && !self.strict
+    }
+
+    /// Tells whether the input looks binary.
+    pub fn is_binary(&self, head: &[u8]) -> bool {
+        // text in UTF-16 and UTF-32 is full of zero bytes, which `content_inspector` takes for
+        // binary, but so is binary data, so it is only trusted to be text if its BOM or the user
+        // says so
+        (self.guessed || !is_wide(self.encoding)) && content_inspector::inspect(head).is_binary()
+    }
+}
+
+/// Detects encoding of the input by its BOM, returning the encoding and length of the BOM.
+fn bom(head: &[u8]) -> Option<(EncodingRef, usize)> {
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some((UTF_8, 3))
+    } else if head.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        Some((&UTF_32LE, 4))
+    } else if head.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        Some((&UTF_32BE, 4))
+    } else if head.starts_with(&[0xFE, 0xFF]) {
+        Some((UTF_16BE, 2))
+    } else if head.starts_with(&[0xFF, 0xFE]) {
+        Some((UTF_16LE, 2))
+    } else {
+        None
+    }
+}
+
+/// Guesses if the input is UTF-16 without BOM, by the zero bytes in every other byte,
+/// which mostly ASCII text encoded in UTF-16 is full of.
+fn guess_utf16(head: &[u8]) -> Option<EncodingRef> {
+    let units = head.len() / 2;
+    if units < 2 {
+        return None;
+    }
+
+    let (mut even_zeros, mut odd_zeros) = (0, 0);
+    for unit in head.chunks_exact(2) {
+        if unit[0] == 0 {
+            even_zeros += 1;
+        }
+        if unit[1] == 0 {
+            odd_zeros += 1;
+        }
+    }
+
+    let mostly = |n: usize| n * 10 >= units * 7;
+    let hardly = |n: usize| n * 10 <= units;
+    if mostly(odd_zeros) && hardly(even_zeros) {
+        Some(UTF_16LE)
+    } else if mostly(even_zeros) && hardly(odd_zeros) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,138 @@
+use crate::Conversion;
+use newline_converter::LineEnding;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+/// A line ending which would be changed by the conversion.
+pub struct Change {
+    /// Number of the line ended by the line ending, starting at 1.
+    pub line: usize,
+    pub from: LineEnding,
+    pub to: String,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8}: {} → {}",
+            self.line,
+            visible(self.from.as_str()),
+            visible(&self.to)
+        )
+    }
+}
+
+/// Replaces line ending characters with their visible representations.
+fn visible(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\r' => "␍".to_owned(),
+            '\n' => "␊".to_owned(),
+            '\u{85}' => "␤".to_owned(),
+            c => format!("<U+{:04X}>", c as u32),
+        })
+        .collect()
+}
+
+/// A writer finding line endings, which would be changed by the conversion, in the UTF-8
+/// written to it.
+pub struct ChangesWriter {
+    conv: Conversion,
+    /// Whether Unicode line endings are treated as line endings.
+    unicode: bool,
+    /// Whether a lone `\r` is treated as a line ending.
+    cr: bool,
+    line: usize,
+    /// Bytes of a line ending which may not be complete yet.
+    pending: Vec<u8>,
+    changes: Vec<Change>,
+}
+
+impl ChangesWriter {
+    pub fn new(conv: Conversion) -> Self {
+        ChangesWriter {
+            conv,
+            // only conversions which replace them treat them as line endings
+            unicode: conv.apply("\u{85}") != "\u{85}",
+            // only conversions which replace it, or produce it, treat it as a line ending
+            cr: conv.apply("\r") != "\r" || conv.target() == LineEnding::Cr,
+            line: 0,
+            pending: Vec::with_capacity(3),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Signals the end of input and returns the changes found.
+    pub fn finish(mut self) -> Vec<Change> {
+        if self.pending == b"\r" {
+            self.lone_cr();
+        }
+
+        self.changes
+    }
+
+    fn lone_cr(&mut self) {
+        if self.cr {
+            self.line_ending(LineEnding::Cr);
+        } else {
+            self.pending.clear();
+        }
+    }
+
+    fn line_ending(&mut self, ending: LineEnding) {
+        self.line += 1;
+        self.pending.clear();
+        let to = self.conv.apply(ending.as_str());
+        if to != ending.as_str() {
+            self.changes.push(Change {
+                line: self.line,
+                from: ending,
+                to: to.into_owned(),
+            });
+        }
+    }
+}
+
+impl Write for ChangesWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            match (&self.pending[..], b) {
+                (b"\r", b'\n') => {
+                    self.line_ending(LineEnding::CrLf);
+                    continue;
+                }
+                (b"\r", _) => self.lone_cr(),
+                ([0xC2], 0x85) => {
+                    self.line_ending(LineEnding::Nel);
+                    continue;
+                }
+                ([0xE2], 0x80) => {
+                    self.pending.push(b);
+                    continue;
+                }
+                ([0xE2, 0x80], 0xA8) => {
+                    self.line_ending(LineEnding::Ls);
+                    continue;
+                }
+                ([0xE2, 0x80], 0xA9) => {
+                    self.line_ending(LineEnding::Ps);
+                    continue;
+                }
+                _ => self.pending.clear(),
+            }
+            match b {
+                b'\n' => self.line_ending(LineEnding::Lf),
+                b'\r' => self.pending.push(b),
+                0xC2 | 0xE2 if self.unicode => self.pending.push(b),
+                _ => {}
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,28 +1,155 @@
+use std::io;
+use std::path::{Path, PathBuf};
 use std::{borrow::Cow, fmt::Display};
 
+/// Exit code when some files could not be processed.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code on invalid command line arguments.
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code used by `--check`, when some of the files need converting.
+pub const EXIT_NEEDS_CONVERSION: i32 = 3;
+
+/// What was being done when an IO error happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read,
+    Write,
+    Rename,
+    Backup,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operation::Read => "read",
+            Operation::Write => "write",
+            Operation::Rename => "replace",
+            Operation::Backup => "back up",
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum RncError {
-    Io(std::io::Error),
-    Encoding(Cow<'static, str>),
+    Io {
+        /// File being processed, if known.
+        path: Option<PathBuf>,
+        operation: Operation,
+        source: io::Error,
+    },
+    /// Invalid input, or output which cannot be represented in the output encoding.
+    Encoding {
+        /// File being processed, if known.
+        path: Option<PathBuf>,
+        /// Byte offset of the invalid sequence in the input, if known.
+        position: Option<usize>,
+        message: Cow<'static, str>,
+    },
+    Walk(ignore::Error),
+    Usage(Cow<'static, str>),
+}
+
+impl RncError {
+    /// Sets the file in which the error happened, unless it is already known.
+    pub fn in_file(self, file: &Path) -> Self {
+        match self {
+            RncError::Io {
+                path: None,
+                operation,
+                source,
+            } => RncError::Io {
+                path: Some(file.to_owned()),
+                operation,
+                source,
+            },
+            RncError::Encoding {
+                path: None,
+                position,
+                message,
+            } => RncError::Encoding {
+                path: Some(file.to_owned()),
+                position,
+                message,
+            },
+            e => e,
+        }
+    }
+
+    /// Returns the exit code for the error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RncError::Usage(_) => EXIT_USAGE,
+            _ => EXIT_FAILURE,
+        }
+    }
 }
 
 impl Display for RncError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RncError::Encoding(m) => write!(f, "{}", m),
-            RncError::Io(e) => write!(f, "{}", e),
+            RncError::Io {
+                path,
+                operation,
+                source,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "could not {}: {}", operation, source)
+            }
+            RncError::Encoding {
+                path,
+                position,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "{}", message)?;
+                if let Some(position) = position {
+                    write!(f, " at byte {}", position)?;
+                }
+                Ok(())
+            }
+            RncError::Walk(e) => write!(f, "{}", e),
+            RncError::Usage(m) => write!(f, "{}", m),
         }
     }
 }
 
-impl From<std::io::Error> for RncError {
-    fn from(e: std::io::Error) -> Self {
-        RncError::Io(e)
+impl From<ignore::Error> for RncError {
+    fn from(e: ignore::Error) -> Self {
+        RncError::Walk(e)
     }
 }
 
-impl From<Cow<'static, str>> for RncError {
-    fn from(m: Cow<'static, str>) -> Self {
-        RncError::Encoding(m)
+/// Adds the context to IO errors.
+pub trait Context<T> {
+    /// Tells what was being done when the error happened.
+    fn context(self, operation: Operation) -> Result<T, RncError>;
+
+    /// Tells what was being done when the error happened, and to which file.
+    fn context_at(self, operation: Operation, path: &Path) -> Result<T, RncError>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn context(self, operation: Operation) -> Result<T, RncError> {
+        self.map_err(|source| match source.kind() {
+            // the output encoding cannot represent a character
+            io::ErrorKind::InvalidData if operation == Operation::Write => RncError::Encoding {
+                path: None,
+                position: None,
+                message: source.to_string().into(),
+            },
+            _ => RncError::Io {
+                path: None,
+                operation,
+                source,
+            },
+        })
+    }
+
+    fn context_at(self, operation: Operation, path: &Path) -> Result<T, RncError> {
+        self.context(operation).map_err(|e| e.in_file(path))
     }
 }
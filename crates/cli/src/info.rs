@@ -0,0 +1,124 @@
+use newline_converter::{analyze, LineEndingStats};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Report about a single file, printed by `--info`.
+pub struct FileInfo {
+    pub stats: LineEndingStats,
+    pub bom: bool,
+    pub encoding: &'static str,
+    pub binary: bool,
+}
+
+/// Format of the `--info` report.
+#[derive(Clone, Copy)]
+pub enum InfoFormat {
+    Table,
+    JsonLines,
+}
+
+impl InfoFormat {
+    /// Returns the header to print before the first file, if any.
+    pub fn header(self) -> Option<String> {
+        match self {
+            InfoFormat::Table => Some(format!(
+                "{:>8} {:>8} {:>8}  {:<3}  {:<8}  {:<6}  FILE",
+                "LF", "CRLF", "CR", "BOM", "ENCODING", "BINARY"
+            )),
+            InfoFormat::JsonLines => None,
+        }
+    }
+
+    /// Formats the report about `filename` as a single line.
+    pub fn format(self, filename: &Path, info: &FileInfo) -> String {
+        match self {
+            InfoFormat::Table => format!(
+                "{:>8} {:>8} {:>8}  {:<3}  {:<8}  {:<6}  {}",
+                info.stats.lf,
+                info.stats.crlf,
+                info.stats.cr,
+                yes_no(info.bom),
+                info.encoding,
+                yes_no(info.binary),
+                filename.display()
+            ),
+            InfoFormat::JsonLines => format!(
+                "{{\"file\":{},\"lf\":{},\"crlf\":{},\"cr\":{},\"bom\":{},\"encoding\":{},\"binary\":{}}}",
+                json_string(&filename.to_string_lossy()),
+                info.stats.lf,
+                info.stats.crlf,
+                info.stats.cr,
+                info.bom,
+                json_string(info.encoding),
+                info.binary
+            ),
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+/// A writer counting line endings in everything written to it.
+#[derive(Default)]
+pub struct LineEndingCounter {
+    stats: LineEndingStats,
+    /// Whether the last write ended with `\r`.
+    trailing_cr: bool,
+}
+
+impl LineEndingCounter {
+    pub fn stats(&self) -> LineEndingStats {
+        self.stats
+    }
+}
+
+impl Write for LineEndingCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let stats = analyze(buf);
+        self.stats.lf += stats.lf;
+        self.stats.crlf += stats.crlf;
+        self.stats.cr += stats.cr;
+        if self.trailing_cr && buf[0] == b'\n' {
+            // `\r\n` split between writes was counted as `\r` and `\n`
+            self.stats.cr -= 1;
+            self.stats.lf -= 1;
+            self.stats.crlf += 1;
+        }
+        self.trailing_cr = buf[buf.len() - 1] == b'\r';
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
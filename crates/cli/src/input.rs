@@ -0,0 +1,56 @@
+use log::debug;
+use memmap2::Mmap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Files at least this large are mapped into memory, smaller ones are cheaper to read.
+const MMAP_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Input file, mapped into memory if it is large, or read in chunks otherwise.
+pub enum FileInput {
+    Mapped(io::Cursor<Mmap>),
+    Read(fs::File),
+}
+
+impl FileInput {
+    /// Opens the file, mapping it into memory if `mmap` is set and it is large enough.
+    pub fn open(path: &Path, mmap: bool) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        if !mmap || file.metadata()?.len() < MMAP_MIN_SIZE {
+            return Ok(FileInput::Read(file));
+        }
+
+        // Safety: the map is only read, and this program never writes to a file while it is
+        // mapped, converted files are replaced with a new one instead. This is not enough to make
+        // the map sound: another program truncating the file while it is mapped makes the reads
+        // past the new end fail with SIGBUS, and one changing it makes the memory change under
+        // us, breaking the `&str` assumed to stay valid UTF-8 once checked. Nothing can prevent
+        // that on files shared with other programs, so the risk is taken for the speed on large
+        // files, like other tools using maps do, and `--no-mmap` is there for avoiding it.
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => Ok(FileInput::Mapped(io::Cursor::new(map))),
+            Err(e) => {
+                debug!("{}: could not map into memory: {}", path.display(), e);
+                Ok(FileInput::Read(file))
+            }
+        }
+    }
+
+    /// Returns the map of the file, along with the position of reading, if it is mapped.
+    pub fn mapped(&self) -> Option<&io::Cursor<Mmap>> {
+        match self {
+            FileInput::Mapped(map) => Some(map),
+            FileInput::Read(_) => None,
+        }
+    }
+}
+
+impl Read for FileInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FileInput::Mapped(map) => map.read(buf),
+            FileInput::Read(file) => file.read(buf),
+        }
+    }
+}
@@ -1,21 +1,106 @@
-use clap::{crate_version, App, Arg};
-use encoding::all::{UTF_16BE, UTF_16LE, UTF_8};
-use encoding::{decode, DecoderTrap, EncoderTrap, EncodingRef};
+use clap::{crate_version, App, Arg, ArgGroup};
+use encoding::all::UTF_8;
+use encoding::{DecoderTrap, EncodingRef};
 use log::{debug, info};
 use simplelog::*;
 use std::borrow::Cow;
 use std::fs;
 use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
 
-use newline_converter::{dos2unix, unix2dos};
+use newline_converter::{Converter, LineEnding};
 
+mod atomic;
+mod backup;
+use backup::Backup;
+mod compare;
+use compare::CompareWriter;
+mod dryrun;
+use dryrun::{Change, ChangesWriter};
+mod detect;
+use detect::InputEncoding;
 mod errors;
-use errors::RncError;
+use errors::{Context, Operation, RncError, EXIT_FAILURE, EXIT_NEEDS_CONVERSION, EXIT_USAGE};
+mod info;
+use info::{FileInfo, InfoFormat, LineEndingCounter};
+mod input;
+use input::FileInput;
+mod pool;
+mod transcode;
+use transcode::{encoding_from_label, is_wide, DecodeError, EncodingWriter, StreamDecoder};
+mod utf32;
+mod walk;
+use walk::FileWalker;
 
-fn main() -> Result<(), RncError> {
+const CHUNK_SIZE: usize = 64 * 1024;
+/// `content_inspector` only looks at this many bytes at the beginning of a file.
+const BINARY_SCAN_SIZE: usize = 1024;
+
+#[derive(Clone, Copy)]
+enum Conversion {
+    Dos2Unix,
+    Unix2Dos,
+    Mac2Unix,
+    Unix2Mac,
+    Mac2Dos,
+    Dos2Mac,
+    Normalize(LineEnding),
+}
+
+impl Conversion {
+    /// Converts `input` in memory.
+    fn apply(self, input: &str) -> Cow<'_, str> {
+        self.converter().convert(input)
+    }
+
+    /// Returns the library conversion, for setting its options.
+    fn converter(self) -> Converter {
+        match self {
+            Conversion::Dos2Unix => Converter::dos2unix(),
+            Conversion::Unix2Dos => Converter::unix2dos(),
+            Conversion::Mac2Unix => Converter::mac2unix(),
+            Conversion::Unix2Mac => Converter::unix2mac(),
+            Conversion::Mac2Dos => Converter::mac2dos(),
+            Conversion::Dos2Mac => Converter::dos2mac(),
+            Conversion::Normalize(target) => Converter::normalize(target),
+        }
+    }
+
+    /// Returns the style of the line endings the conversion produces.
+    fn target(self) -> LineEnding {
+        match self {
+            Conversion::Dos2Unix | Conversion::Mac2Unix => LineEnding::Lf,
+            Conversion::Unix2Dos | Conversion::Mac2Dos => LineEnding::CrLf,
+            Conversion::Unix2Mac | Conversion::Dos2Mac => LineEnding::Cr,
+            Conversion::Normalize(target) => target,
+        }
+    }
+}
+
+fn main() {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            e.exit_code()
+        }
+    };
+    process::exit(code);
+}
+
+/// Runs the program, returning the exit code.
+fn run() -> Result<i32, RncError> {
     let matches = App::new("rnc")
         .version(crate_version!())
         .about("Newline byte(s) converter")
+        .after_help(
+            "EXIT STATUS:\n    \
+             0    Success\n    \
+             1    Some files could not be processed\n    \
+             2    Invalid command line arguments\n    \
+             3    --check found files which need converting",
+        )
         .arg(Arg::with_name("FILE")
             .help("Sets the input file to use. If not set, processes stdin to stdout")
             .takes_value(true)
@@ -23,15 +108,49 @@ fn main() -> Result<(), RncError> {
         )
         .arg(Arg::with_name("dos2unix")
             .long("dos2unix")
-            .required_unless("unix2dos")
-            .conflicts_with("unix2dos")
             .help("Convert DOS line endings to Unix (\\r\\n -> \\n)")
         )
         .arg(Arg::with_name("unix2dos")
             .long("unix2dos")
-            .required_unless("dos2unix")
             .help("Convert Unix line endings to DOS (\\n -> \\r\\n)")
         )
+        .arg(Arg::with_name("mac2unix")
+            .long("mac2unix")
+            .help("Convert classic Mac line endings to Unix (\\r -> \\n)")
+        )
+        .arg(Arg::with_name("unix2mac")
+            .long("unix2mac")
+            .help("Convert Unix line endings to classic Mac (\\n -> \\r)")
+        )
+        .arg(Arg::with_name("mac2dos")
+            .long("mac2dos")
+            .help("Convert classic Mac line endings to DOS (\\r -> \\r\\n)")
+        )
+        .arg(Arg::with_name("dos2mac")
+            .long("dos2mac")
+            .help("Convert DOS line endings to classic Mac (\\r\\n -> \\r)")
+        )
+        .arg(Arg::with_name("TO")
+            .long("to")
+            .takes_value(true)
+            .possible_values(&["lf", "crlf", "cr"])
+            .help("Convert all line endings (including Unicode NEL, LS and PS) to TO")
+        )
+        .arg(Arg::with_name("INFO")
+            .long("info")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .value_name("FORMAT")
+            .possible_values(&["table", "json"])
+            .requires("FILE")
+            .help("Don't convert anything, print line ending counts, BOM, encoding and binary detection of each FILE instead. \
+                   FORMAT is either a table (default), or JSON lines")
+        )
+        .group(ArgGroup::with_name("MODE")
+            .args(&["dos2unix", "unix2dos", "mac2unix", "unix2mac", "mac2dos", "dos2mac", "TO", "INFO"])
+            .required(true)
+        )
         .arg(Arg::with_name("OUT")
             .short("o")
             .long("output")
@@ -41,9 +160,126 @@ fn main() -> Result<(), RncError> {
         .arg(Arg::with_name("ENCODE")
             .short("e")
             .long("encode")
-            .help("Encode output in given encoding")
+            .help("Encode output in given encoding: any WHATWG label (like utf-16be, windows-1250, iso-8859-2 \
+                   or shift_jis), or utf-32le/utf-32be. Fails on characters which cannot be represented in it")
+            .takes_value(true)
+            .validator(|label| match encoding_from_label(&label) {
+                Some(_) => Ok(()),
+                None => Err(format!("unknown encoding: {}", label)),
+            })
+        )
+        .arg(Arg::with_name("FROM_ENCODING")
+            .long("from-encoding")
+            .takes_value(true)
+            .value_name("LABEL")
+            .validator(|label| match encoding_from_label(&label) {
+                Some(_) => Ok(()),
+                None => Err(format!("unknown encoding: {}", label)),
+            })
+            .help("Decode input in given encoding (any WHATWG label, like windows-1252 or iso-8859-2), \
+                   instead of detecting it. Detection recognizes BOMs and UTF-16 without BOM (which looks \
+                   binary, so it is only converted with --force), otherwise UTF-8 is assumed, and inputs \
+                   which aren't valid UTF-8 are converted as they are, as long as their encoding is \
+                   ASCII-compatible (they are refused with --strict or --encode)")
+        )
+        .arg(Arg::with_name("STRICT")
+            .long("strict")
+            .help("Fail on invalid input, reporting its position, instead of decoding it as replacement characters")
+        )
+        .arg(Arg::with_name("BOM")
+            .long("bom")
+            .takes_value(true)
+            .possible_values(&["add", "remove", "keep"])
+            .help("Add a byte order mark to the output (if its encoding has one), remove it, or keep it only if \
+                   the input has one. By default, it is kept, and also added when encoding output in UTF-16 \
+                   or UTF-32 with --encode")
+        )
+        .arg(Arg::with_name("CHECK")
+            .long("check")
+            .requires("FILE")
+            .conflicts_with("OUT")
+            .help("Don't write anything, only list the FILEs that would be converted. Exits with code 3 if there are any")
+        )
+        .arg(Arg::with_name("RECURSIVE")
+            .short("r")
+            .long("recursive")
+            .help("Process all files in directories given as FILE, recursively")
+        )
+        .arg(Arg::with_name("INCLUDE")
+            .long("include")
+            .takes_value(true)
+            .value_name("GLOB")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("RECURSIVE")
+            .help("Only process files matching GLOB when walking directories. Can be given multiple times")
+        )
+        .arg(Arg::with_name("EXCLUDE")
+            .long("exclude")
             .takes_value(true)
-            .possible_values(&["utf8", "utf16", "utf16be"])
+            .value_name("GLOB")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("RECURSIVE")
+            .help("Skip files matching GLOB when walking directories. Can be given multiple times")
+        )
+        .arg(Arg::with_name("GITIGNORE")
+            .long("gitignore")
+            .requires("RECURSIVE")
+            .help("Skip files ignored by .gitignore, and the .git directory, when walking directories")
+        )
+        .arg(Arg::with_name("ADD_EOL")
+            .long("add-eol")
+            .help("Add a line ending at the end of each FILE, if it is missing")
+        )
+        .arg(Arg::with_name("TRIM_TRAILING_NEWLINES")
+            .long("trim-trailing-newlines")
+            .help("Remove blank lines at the end of each FILE, leaving a single line ending")
+        )
+        .arg(Arg::with_name("STRIP_TRAILING_WHITESPACE")
+            .long("strip-trailing-whitespace")
+            .help("Remove spaces and tabs at the end of each line")
+        )
+        .arg(Arg::with_name("KEEP_DATE")
+            .short("k")
+            .long("keep-date")
+            .help("Keep the access and modification times of FILE (the output gets the ones of FILE, if OUT is given)")
+        )
+        .arg(Arg::with_name("DRY_RUN")
+            .long("dry-run")
+            .requires("FILE")
+            // only changed line endings are reported, not other edits to the text
+            .conflicts_with_all(&["OUT", "CHECK", "ADD_EOL", "TRIM_TRAILING_NEWLINES", "STRIP_TRAILING_WHITESPACE"])
+            .help("Don't write anything, print the line endings which would be changed in each FILE instead")
+        )
+        .arg(Arg::with_name("BACKUP")
+            .long("backup")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .value_name("SUFFIX")
+            .help("Keep a copy of each overwritten file, named with SUFFIX appended (.orig by default). \
+                   With --backup=numbered, keep numbered backups (FILE.~1~, FILE.~2~, ...)")
+        )
+        .arg(Arg::with_name("NO_MMAP")
+            .long("no-mmap")
+            .help("Never map large FILEs into memory, read them in chunks instead")
+        )
+        .arg(Arg::with_name("FAIL_FAST")
+            .long("fail-fast")
+            .help("Stop at the first FILE which cannot be processed, instead of going on with the rest")
+        )
+        .arg(Arg::with_name("JOBS")
+            .short("j")
+            .long("jobs")
+            .takes_value(true)
+            .value_name("N")
+            .requires("FILE")
+            .validator(|n| match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(()),
+                _ => Err("must be a positive number".to_owned()),
+            })
+            .help("Process N files at a time, on separate threads")
         )
         .arg(Arg::with_name("FORCE")
             .short("f")
@@ -60,7 +296,16 @@ fn main() -> Result<(), RncError> {
             .long("debug")
             .help("Print out debug info")
         )
-        .get_matches();
+        .get_matches_safe();
+    let matches = match matches {
+        Ok(m) => m,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{}", e.message);
+            return Ok(EXIT_USAGE);
+        }
+        // --help and --version
+        Err(e) => e.exit(),
+    };
 
     let verbose = matches.is_present("verbose");
     let debug = matches.is_present("debug");
@@ -72,133 +317,666 @@ fn main() -> Result<(), RncError> {
         SimpleLogger::init(LevelFilter::Off, Config::default()).expect("could not init logger");
     }
 
-    let encode: Option<EncodingRef> = matches.value_of("ENCODE").map(|x| match x {
-        "utf16" => UTF_16LE as EncodingRef,
-        "utf16be" => UTF_16BE as EncodingRef,
-        _ => UTF_8 as EncodingRef,
-    });
+    let encode = matches.value_of("ENCODE").and_then(encoding_from_label);
+    let from_encoding = matches
+        .value_of("FROM_ENCODING")
+        .and_then(encoding_from_label);
+
+    let fail_fast = matches.is_present("FAIL_FAST");
+    let mmap = !matches.is_present("NO_MMAP");
+    let jobs = matches
+        .value_of("JOBS")
+        .and_then(|j| j.parse().ok())
+        .unwrap_or(1);
+
+    let mut summary = Summary::default();
+    let files = match matches.values_of_os("FILE") {
+        Some(paths) => {
+            let walker = FileWalker::new(matches.is_present("RECURSIVE"))
+                .include(matches.values_of("INCLUDE").into_iter().flatten())
+                .exclude(matches.values_of("EXCLUDE").into_iter().flatten())
+                .gitignore(matches.is_present("GITIGNORE"));
+            let mut files = Vec::new();
+            for path in paths {
+                for e in walker.collect(Path::new(path), &mut files)? {
+                    summary.record_error(e.into(), fail_fast)?;
+                }
+            }
+            Some(files)
+        }
+        None => None,
+    };
+
+    if matches.is_present("INFO") {
+        let format = match matches.value_of("INFO") {
+            Some("json") => InfoFormat::JsonLines,
+            _ => InfoFormat::Table,
+        };
+        if let Some(header) = format.header() {
+            println!("{}", header);
+        }
+        pool::for_each_file(
+            &files.unwrap(),
+            jobs,
+            |f| file_info(f, from_encoding, mmap),
+            |f, result| {
+                let result = result.map(|info| {
+                    println!("{}", format.format(f, &info));
+                    Outcome::Done
+                });
+                summary.record(f, result, fail_fast)
+            },
+        )?;
+        return Ok(summary.finish());
+    }
 
-    #[allow(clippy::type_complexity)]
-    let conv: Box<dyn Fn(&str) -> Cow<str>> = if matches.is_present("dos2unix") {
-        Box::new(dos2unix)
+    let conv = if matches.is_present("dos2unix") {
+        Conversion::Dos2Unix
     } else if matches.is_present("unix2dos") {
-        Box::new(unix2dos)
+        Conversion::Unix2Dos
+    } else if matches.is_present("mac2unix") {
+        Conversion::Mac2Unix
+    } else if matches.is_present("unix2mac") {
+        Conversion::Unix2Mac
+    } else if matches.is_present("mac2dos") {
+        Conversion::Mac2Dos
+    } else if matches.is_present("dos2mac") {
+        Conversion::Dos2Mac
+    } else if let Some(to) = matches.value_of("TO") {
+        Conversion::Normalize(match to {
+            "crlf" => LineEnding::CrLf,
+            "cr" => LineEnding::Cr,
+            _ => LineEnding::Lf,
+        })
     } else {
         unreachable!()
     };
 
-    if matches.is_present("OUT")
-        && matches.is_present("FILE")
-        && matches.values_of("FILE").unwrap().len() > 1
-    {
-        return Err(RncError::Io(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "OUT cannot be used with multiple FILEs",
-        )));
+    if matches.is_present("OUT") && files.as_ref().map(Vec::len).unwrap_or(0) > 1 {
+        return Err(RncError::Usage(
+            "OUT cannot be used with multiple FILEs".into(),
+        ));
     }
 
-    let output = matches.value_of("OUT");
+    let output = matches.value_of_os("OUT").map(Path::new);
+    let options = Options {
+        conv,
+        force_binary: matches.is_present("FORCE"),
+        from_encoding,
+        encode,
+        strict: matches.is_present("STRICT"),
+        bom: matches.value_of("BOM").map(|x| match x {
+            "add" => BomMode::Add,
+            "remove" => BomMode::Remove,
+            _ => BomMode::Keep,
+        }),
+        add_eol: matches.is_present("ADD_EOL"),
+        trim_trailing_newlines: matches.is_present("TRIM_TRAILING_NEWLINES"),
+        strip_trailing_whitespace: matches.is_present("STRIP_TRAILING_WHITESPACE"),
+        keep_date: matches.is_present("KEEP_DATE"),
+        mmap,
+        backup: if matches.is_present("BACKUP") {
+            Some(Backup::new(matches.value_of("BACKUP")))
+        } else {
+            None
+        },
+    };
 
-    if encode.is_some() {
-        debug!("Forced output encoding: {}", encode.unwrap().name());
+    if let Some(e) = encode {
+        debug!("Forced output encoding: {}", e.name());
     }
 
-    if let Some(filenames) = matches.values_of("FILE") {
-        for f in filenames {
+    if let Some(files) = files {
+        let check = matches.is_present("CHECK");
+        let dry_run = matches.is_present("DRY_RUN");
+        let work = |f: &Path| {
+            if check {
+                check_file(f, &options).map(Report::Checked)
+            } else if dry_run {
+                dry_run_file(f, &options).map(Report::Changes)
+            } else {
+                process_file(f, output.unwrap_or(f), &options).map(Report::Converted)
+            }
+        };
+
+        pool::for_each_file(&files, jobs, work, |f, result| {
             if verbose {
-                println!("Processing {} ", f);
+                println!("Processing {} ", f.display());
             }
-            let o = output.unwrap_or(f);
-            let r = process_file(f, o, &conv, matches.is_present("FORCE"), encode)?;
-            let FileProcessingResult(processed, read, write) = r;
-            if processed {
-                info!("{}: {} bytes read. {} bytes written", f, read, write);
-            } else {
-                info!("{}: Skipped binary file", f);
+            let result = result.map(|report| match report {
+                Report::Checked(Some(true)) => {
+                    println!("{}", f.display());
+                    Outcome::NeedsConversion
+                }
+                Report::Checked(Some(false)) => {
+                    info!("{}: Already converted", f.display());
+                    Outcome::Done
+                }
+                Report::Changes(Some(changes)) => {
+                    if changes.is_empty() {
+                        info!("{}: Already converted", f.display());
+                    } else {
+                        println!("{}", f.display());
+                        for change in changes {
+                            println!("{}", change);
+                        }
+                    }
+                    Outcome::Done
+                }
+                Report::Converted(Some(processed)) => {
+                    info!(
+                        "{}: converted {} line breaks. {} bytes read. {} bytes written",
+                        f.display(),
+                        thousands(processed.converted),
+                        processed.read,
+                        processed.written
+                    );
+                    Outcome::Done
+                }
+                Report::Checked(None) | Report::Changes(None) | Report::Converted(None) => {
+                    info!("{}: Skipped binary file", f.display());
+                    Outcome::Skipped
+                }
+            });
+            summary.record(f, result, fail_fast)
+        })?;
+        Ok(summary.finish())
+    } else {
+        process_stdio(output, &options)?;
+        Ok(0)
+    }
+}
+
+/// Result of the work on a file, reported in the order of the files.
+enum Report {
+    Converted(Option<Processed>),
+    Checked(Option<bool>),
+    Changes(Option<Vec<Change>>),
+}
+
+/// Formats `n` with thousands separated by commas.
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::with_capacity(digits.len() * 4 / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// What happened to a file.
+enum Outcome {
+    Done,
+    Skipped,
+    /// Checked, and found to need converting.
+    NeedsConversion,
+}
+
+/// Counts of files by what happened to them.
+#[derive(Default)]
+struct Summary {
+    done: usize,
+    skipped: usize,
+    failed: usize,
+    needs_conversion: usize,
+}
+
+impl Summary {
+    /// Records the result of processing `file`. Errors are printed and counted,
+    /// unless `fail_fast` is set, in which case they are returned.
+    fn record(
+        &mut self,
+        file: &Path,
+        result: Result<Outcome, RncError>,
+        fail_fast: bool,
+    ) -> Result<(), RncError> {
+        match result.map_err(|e| e.in_file(file)) {
+            Ok(Outcome::Done) => self.done += 1,
+            Ok(Outcome::Skipped) => self.skipped += 1,
+            Ok(Outcome::NeedsConversion) => {
+                self.done += 1;
+                self.needs_conversion += 1;
             }
+            Err(e) => return self.record_error(e, fail_fast),
         }
-    } else {
-        process_stdio(conv, output, encode)?;
+
+        Ok(())
     }
 
-    Ok(())
+    /// Records an error. It is printed and counted, unless `fail_fast` is set, in which case
+    /// it is returned.
+    fn record_error(&mut self, e: RncError, fail_fast: bool) -> Result<(), RncError> {
+        if fail_fast {
+            return Err(e);
+        }
+        eprintln!("error: {}", e);
+        self.failed += 1;
+
+        Ok(())
+    }
+
+    /// Prints the summary, if there were any failures among several files or if verbose, and
+    /// returns the exit code.
+    fn finish(self) -> i32 {
+        let message = format!(
+            "{} files processed, {} skipped, {} failed",
+            self.done, self.skipped, self.failed
+        );
+        if self.failed > 0 {
+            if self.done + self.skipped > 0 {
+                eprintln!("{}", message);
+            }
+            EXIT_FAILURE
+        } else {
+            info!("{}", message);
+            if self.needs_conversion > 0 {
+                EXIT_NEEDS_CONVERSION
+            } else {
+                0
+            }
+        }
+    }
 }
 
-fn process_stdio<F>(
-    conv: F,
-    outfile: Option<&str>,
-    encode: Option<EncodingRef>,
-) -> Result<(), RncError>
-where
-    F: Fn(&str) -> std::borrow::Cow<str>,
-{
+fn process_stdio(outfile: Option<&Path>, options: &Options) -> Result<(), RncError> {
     let stdin = io::stdin();
-    let mut out: Box<dyn Write> = match outfile {
-        Some(f) => Box::new(fs::File::create(f)?),
+    let out: Box<dyn Write> = match outfile {
+        Some(f) => Box::new(fs::File::create(f).context_at(Operation::Write, f)?),
         None => Box::new(io::stdout()),
     };
-    let mut buffer: Vec<u8> = Vec::new();
-    stdin.lock().read_to_end(&mut buffer)?;
-    process(
-        &buffer,
-        conv,
-        encode.or(Some(UTF_8 as EncodingRef)),
-        &mut out,
-    )?;
+    let (mut input_encoding, input) =
+        sniff(stdin.lock(), options.from_encoding).context(Operation::Read)?;
+    input_encoding.strict = options.strict;
+    // output is UTF-8, unless input in an unknown encoding is converted as it is
+    let encoding = match options.encode {
+        None if input_encoding.is_raw() => None,
+        encoding => encoding.or(Some(UTF_8 as EncodingRef)),
+    };
+    process(input, &input_encoding, options, encoding, out)?;
 
     Ok(())
 }
 
-struct FileProcessingResult(bool, usize, usize);
+/// What to do with the byte order mark, set by `--bom`.
+#[derive(Clone, Copy)]
+enum BomMode {
+    Add,
+    Remove,
+    Keep,
+}
 
-fn process_file<F>(
-    filename: &str,
-    out: &str,
-    conv: F,
+/// Options of converting files, set by the command line arguments.
+struct Options {
+    conv: Conversion,
     force_binary: bool,
+    from_encoding: Option<EncodingRef>,
     encode: Option<EncodingRef>,
-) -> Result<FileProcessingResult, RncError>
+    strict: bool,
+    bom: Option<BomMode>,
+    add_eol: bool,
+    trim_trailing_newlines: bool,
+    strip_trailing_whitespace: bool,
+    keep_date: bool,
+    /// Whether large files may be mapped into memory.
+    mmap: bool,
+    backup: Option<Backup>,
+}
+
+impl Options {
+    /// Returns the library conversion, with the options changing the text set.
+    fn converter(&self) -> Converter {
+        self.conv
+            .converter()
+            .strip_trailing_whitespace(self.strip_trailing_whitespace)
+            .add_final_newline(self.add_eol)
+            .trim_trailing_newlines(self.trim_trailing_newlines)
+    }
+}
+
+/// Sizes of the input and output, and the number of line endings converted.
+struct Processed {
+    read: usize,
+    written: usize,
+    converted: usize,
+}
+
+fn process_file(
+    filename: &Path,
+    out: &Path,
+    options: &Options,
+) -> Result<Option<Processed>, RncError> {
+    let (input_encoding, input) = match open_file(filename, options)? {
+        Some(input) => input,
+        None => return Ok(None),
+    };
+    // taken before the file is replaced
+    let metadata = fs::metadata(filename).context(Operation::Read)?;
+    let convert =
+        |output: &mut dyn Write| process(input, &input_encoding, options, options.encode, output);
+
+    let processed = if out.exists() {
+        let original = fs::File::open(out).context_at(Operation::Read, out)?;
+        atomic::replace_file(out, |output| {
+            let mut output = CompareWriter::new(io::BufReader::new(original), output);
+            let processed = convert(&mut output)?;
+            // files already converted are left alone, without a backup
+            let changed = output.finish().context_at(Operation::Read, out)?;
+            if changed {
+                if let Some(backup) = &options.backup {
+                    let path = backup.create(out).context_at(Operation::Backup, out)?;
+                    info!("{}: Backed up to {}", out.display(), path.display());
+                }
+            }
+            Ok((processed, changed))
+        })?
+    } else {
+        let r = convert(&mut fs::File::create(out).context_at(Operation::Write, out)?);
+        if r.is_err() {
+            // don't leave a partially written output behind
+            let _ = fs::remove_file(out);
+        }
+        r.map_err(|e| e.in_file(out))?
+    };
+    if options.keep_date {
+        atomic::copy_times(&metadata, out).context_at(Operation::Write, out)?;
+    }
+
+    Ok(Some(processed))
+}
+
+/// Checks whether converting the file would change it. Returns `None` for skipped binary files.
+fn check_file(filename: &Path, options: &Options) -> Result<Option<bool>, RncError> {
+    let (input_encoding, input) = match open_file(filename, options)? {
+        Some(input) => input,
+        None => return Ok(None),
+    };
+
+    let original = fs::File::open(filename).context(Operation::Read)?;
+    let mut original = CompareWriter::new(io::BufReader::new(original), io::sink());
+    process(
+        input,
+        &input_encoding,
+        options,
+        options.encode,
+        &mut original,
+    )?;
+
+    Ok(Some(original.finish().context(Operation::Read)?))
+}
+
+/// Finds the line endings which converting the file would change. Returns `None` for skipped binary files.
+fn dry_run_file(filename: &Path, options: &Options) -> Result<Option<Vec<Change>>, RncError> {
+    let (input_encoding, mut input) = match open_file(filename, options)? {
+        Some(input) => input,
+        None => return Ok(None),
+    };
+
+    let mut changes = ChangesWriter::new(options.conv);
+    if input_encoding.is_raw() {
+        copy_raw(&mut input, &mut changes)?;
+    } else {
+        decode(
+            &mut input,
+            &input_encoding,
+            input_encoding.decoder_trap(),
+            &mut changes,
+        )?;
+    }
+
+    Ok(Some(changes.finish()))
+}
+
+/// Inspects the file for `--info`.
+fn file_info(
+    filename: &Path,
+    from_encoding: Option<EncodingRef>,
+    mmap: bool,
+) -> Result<FileInfo, RncError> {
+    let input = FileInput::open(filename, mmap).context(Operation::Read)?;
+    let (input_encoding, mut input) = sniff(input, from_encoding).context(Operation::Read)?;
+    let binary = content_inspector::inspect(input.get_ref().0.get_ref()).is_binary();
+
+    // UTF-8 is only assumed, so it is reported only if the input really is valid UTF-8
+    let trap = if input_encoding.assumed {
+        DecoderTrap::Strict
+    } else {
+        DecoderTrap::Replace
+    };
+    let mut counter = LineEndingCounter::default();
+    let encoding = match decode(&mut input, &input_encoding, trap, &mut counter) {
+        Ok(_) => input_encoding.encoding.name(),
+        Err(RncError::Encoding { .. }) if input_encoding.assumed => {
+            // most likely some ASCII-compatible encoding, with line endings as they are in UTF-8
+            counter = LineEndingCounter::default();
+            let mut input = FileInput::open(filename, mmap).context(Operation::Read)?;
+            io::copy(&mut input, &mut counter).context(Operation::Read)?;
+            "unknown"
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(FileInfo {
+        stats: counter.stats(),
+        bom: input_encoding.bom_len > 0,
+        encoding,
+        binary,
+    })
+}
+
+/// Opens the file for reading, past its BOM. Returns `None` if it is binary, unless forced.
+fn open_file(
+    filename: &Path,
+    options: &Options,
+) -> Result<Option<(InputEncoding, Sniffed<FileInput>)>, RncError> {
+    let input = FileInput::open(filename, options.mmap).context(Operation::Read)?;
+    let (mut input_encoding, input) =
+        sniff(input, options.from_encoding).context(Operation::Read)?;
+    input_encoding.strict = options.strict;
+
+    if input_encoding.is_binary(input.get_ref().0.get_ref()) && !options.force_binary {
+        return Ok(None);
+    }
+
+    Ok(Some((input_encoding, input)))
+}
+
+/// Input with its beginning, read for detection, put back.
+type Sniffed<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+/// Input, which may be in memory whole, to be converted without copying.
+trait Input: Read {
+    /// Returns the rest of the input, if it is all in memory.
+    fn in_memory(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<'a> Input for Sniffed<io::StdinLock<'a>> {}
+
+impl Input for Sniffed<FileInput> {
+    fn in_memory(&self) -> Option<&[u8]> {
+        let (head, rest) = self.get_ref();
+        let map = rest.mapped()?;
+        // the head is a copy of the beginning of the map, valid only if nothing else was read yet
+        if map.position() != head.get_ref().len() as u64 {
+            return None;
+        }
+        Some(&map.get_ref()[head.position() as usize..])
+    }
+}
+
+/// Detects encoding of the input, unless `from_encoding` is given. Returns it along with
+/// the input positioned past its BOM.
+fn sniff<R: Read>(
+    mut input: R,
+    from_encoding: Option<EncodingRef>,
+) -> io::Result<(InputEncoding, Sniffed<R>)> {
+    let head = read_head(&mut input, BINARY_SCAN_SIZE)?;
+    let input_encoding = InputEncoding::detect(&head, from_encoding);
+    debug!("Input encoding: {}", input_encoding.encoding.name());
+    let mut input = io::Cursor::new(head).chain(input);
+    input
+        .get_mut()
+        .0
+        .set_position(input_encoding.bom_len as u64);
+
+    Ok((input_encoding, input))
+}
+
+/// Reads up to `size` bytes from the beginning of `input`.
+fn read_head<R: Read>(input: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(size);
+    input.take(size as u64).read_to_end(&mut head)?;
+
+    Ok(head)
+}
+
+/// Converts `input`, positioned past its BOM, and writes it to `output` in `encoding`,
+/// or the input encoding if not given.
+fn process<R, W>(
+    mut input: R,
+    input_encoding: &InputEncoding,
+    options: &Options,
+    encoding: Option<EncodingRef>,
+    output: W,
+) -> Result<Processed, RncError>
 where
-    F: Fn(&str) -> std::borrow::Cow<str>,
+    R: Input,
+    W: Write,
 {
-    let content = fs::read(filename)?;
+    // input in an unknown encoding can't be re-encoded, so it has to be decoded for that
+    let raw = input_encoding.is_raw() && encoding.is_none();
+    let encode_with = encoding.unwrap_or(input_encoding.encoding);
+    let mut output = if raw {
+        EncodingWriter::raw(io::BufWriter::new(output))
+    } else {
+        EncodingWriter::new(io::BufWriter::new(output), encode_with)
+    };
+    let has_bom = input_encoding.bom_len > 0;
+    let write_bom = match options.bom {
+        Some(BomMode::Add) => true,
+        Some(BomMode::Remove) => false,
+        Some(BomMode::Keep) => has_bom,
+        // BOM-less UTF-16 and UTF-32 are hard to recognize, so they get one when re-encoding
+        None => has_bom || (encoding.is_some() && is_wide(encode_with)),
+    };
+    if write_bom {
+        output.write_bom().context(Operation::Write)?;
+    }
+    let mut output = options.converter().writer(output);
+    let read = if raw {
+        copy_raw(&mut input, &mut output)?
+    } else {
+        decode(
+            &mut input,
+            input_encoding,
+            input_encoding.decoder_trap(),
+            &mut output,
+        )?
+    };
+    let (output, converted) = output.finish_counted().context(Operation::Write)?;
 
-    let binary = content_inspector::inspect(&content).is_binary();
+    Ok(Processed {
+        read: input_encoding.bom_len + read,
+        written: output.finish().context(Operation::Write)?,
+        converted,
+    })
+}
 
-    if binary && !force_binary {
-        return Ok(FileProcessingResult(false, content.len(), 0));
+/// Writes the whole `input` to `output` as it is. Returns the number of bytes read.
+fn copy_raw<R, W>(input: &mut R, output: &mut W) -> Result<usize, RncError>
+where
+    R: Input,
+    W: Write,
+{
+    if let Some(bytes) = input.in_memory() {
+        output.write_all(bytes).context(Operation::Write)?;
+        return Ok(bytes.len());
     }
 
-    let mut f = fs::File::create(out)?;
-    let outlen = process(&content, conv, encode, &mut f)?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut read = 0;
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context(Operation::Read),
+        };
+        read += n;
+        output.write_all(&buffer[..n]).context(Operation::Write)?;
+    }
 
-    Ok(FileProcessingResult(true, content.len(), outlen))
+    Ok(read)
 }
 
-fn process<F, O>(
-    input: &[u8],
-    conv: F,
-    encoding: Option<EncodingRef>,
-    output: &mut O,
+/// Decodes the whole `input` and writes it as UTF-8 to `output`. Returns the number of bytes read.
+fn decode<R, W>(
+    input: &mut R,
+    input_encoding: &InputEncoding,
+    trap: DecoderTrap,
+    output: &mut W,
 ) -> Result<usize, RncError>
 where
-    F: Fn(&str) -> std::borrow::Cow<str>,
-    O: Write,
+    R: Input,
+    W: Write,
 {
-    let (decoding_result, detected_encoding) = decode(input, DecoderTrap::Replace, UTF_8);
-    debug!("Detected encoding: {}", detected_encoding.name());
-    let as_string = decoding_result?;
-    let converted = conv(&as_string);
-    let encode_with = encoding.unwrap_or(detected_encoding);
-    let encoded = encode_with.encode(&converted, EncoderTrap::Replace)?;
-    let bom: Vec<u8> = match encode_with.name() {
-        "utf-16le" => vec![0xFF, 0xFE],
-        "utf-16be" => vec![0xFE, 0xFF],
-        _ => vec![],
-    };
-    output.write_all(&bom)?;
-    output.write_all(&encoded)?;
-    output.flush()?;
-
-    Ok(encoded.len())
+    // UTF-8 in memory needs validating only, and is converted from there, without copying
+    if input_encoding.encoding.name() == "utf-8" {
+        if let Some(text) = input.in_memory().and_then(|i| std::str::from_utf8(i).ok()) {
+            output
+                .write_all(text.as_bytes())
+                .context(Operation::Write)?;
+            return Ok(text.len());
+        }
+    }
+
+    let mut decoder = StreamDecoder::new(input_encoding.encoding, trap);
+    let explain = |e: DecodeError| RncError::Encoding {
+        path: None,
+        position: Some(input_encoding.bom_len + e.position),
+        message: if input_encoding.assumed {
+            format!(
+                "{} (the input is not valid UTF-8, use --from-encoding to set its encoding)",
+                e.cause
+            )
+            .into()
+        } else if input_encoding.guessed {
+            format!(
+                "{} (the input was guessed to be {}, use --from-encoding to set its encoding)",
+                e.cause,
+                input_encoding.encoding.name()
+            )
+            .into()
+        } else {
+            e.cause
+        },
+    };
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut decoded = String::with_capacity(CHUNK_SIZE);
+    let mut read = 0;
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context(Operation::Read),
+        };
+        read += n;
+        decoded.clear();
+        decoder.feed(&buffer[..n], &mut decoded).map_err(explain)?;
+        output
+            .write_all(decoded.as_bytes())
+            .context(Operation::Write)?;
+    }
+    decoded.clear();
+    decoder.finish(&mut decoded).map_err(explain)?;
+    output
+        .write_all(decoded.as_bytes())
+        .context(Operation::Write)?;
+
+    Ok(read)
 }
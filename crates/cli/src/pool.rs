@@ -0,0 +1,63 @@
+use crate::errors::RncError;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+/// Runs `work` on each of `files` using `jobs` threads, and passes the results to `report` in the
+/// order of the files, as soon as all the files before are reported.
+///
+/// Stops taking new files when `report` returns an error, and returns it once the files already
+/// being worked on are done.
+pub fn for_each_file<T, W, R>(
+    files: &[PathBuf],
+    jobs: usize,
+    work: W,
+    mut report: R,
+) -> Result<(), RncError>
+where
+    T: Send,
+    W: Fn(&Path) -> T + Sync,
+    R: FnMut(&Path, T) -> Result<(), RncError>,
+{
+    if jobs <= 1 || files.len() <= 1 {
+        for f in files {
+            report(f, work(f))?;
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    crossbeam_utils::thread::scope(|scope| {
+        for _ in 0..jobs.min(files.len()) {
+            let (tx, next, stop, work) = (tx.clone(), &next, &stop, &work);
+            scope.spawn(move |_| {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= files.len() || tx.send((i, work(&files[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // results come in the order they are done, so keep them until their turn
+        let mut done = BTreeMap::new();
+        let mut turn = 0;
+        for (i, result) in rx {
+            done.insert(i, result);
+            while let Some(result) = done.remove(&turn) {
+                if let Err(e) = report(&files[turn], result) {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+                turn += 1;
+            }
+        }
+        Ok(())
+    })
+    .expect("worker thread panicked")
+}
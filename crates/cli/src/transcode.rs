@@ -0,0 +1,270 @@
+use crate::utf32::{UTF_32BE, UTF_32LE};
+use encoding::all::{UTF_16BE, UTF_16LE, UTF_8};
+use encoding::{DecoderTrap, EncodingRef, RawDecoder, RawEncoder};
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Finds an encoding by its WHATWG label, or one of `utf-32le` and `utf-32be`.
+/// Also accepts `utf16` and `utf16be`, which `--encode` used to take.
+pub fn encoding_from_label(label: &str) -> Option<EncodingRef> {
+    match &label.trim().to_ascii_lowercase()[..] {
+        "utf-32" | "utf-32le" | "utf32" => Some(&UTF_32LE),
+        "utf-32be" | "utf32be" => Some(&UTF_32BE),
+        "utf16" => Some(UTF_16LE),
+        "utf16be" => Some(UTF_16BE),
+        label => encoding::label::encoding_from_whatwg_label(label),
+    }
+}
+
+/// Error of decoding, at given byte offset of the input.
+pub struct DecodeError {
+    pub position: usize,
+    pub cause: Cow<'static, str>,
+}
+
+/// Incrementally decodes chunks of input into UTF-8.
+///
+/// Multi-byte sequences split between chunks are handled correctly.
+pub struct StreamDecoder {
+    decoder: Box<dyn RawDecoder>,
+    trap: DecoderTrap,
+    /// Bytes already fed to `decoder`, but not processed yet.
+    unprocessed: Vec<u8>,
+    /// Number of bytes fed so far.
+    position: usize,
+}
+
+impl StreamDecoder {
+    pub fn new(encoding: EncodingRef, trap: DecoderTrap) -> Self {
+        StreamDecoder {
+            decoder: encoding.raw_decoder(),
+            trap,
+            unprocessed: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Decodes `input`, appending the result to `output`.
+    pub fn feed(&mut self, input: &[u8], output: &mut String) -> Result<(), DecodeError> {
+        // offset of `input` in the whole input
+        let mut base = self.position;
+        self.position += input.len();
+        let mut input = Cow::Borrowed(input);
+        let mut remaining = 0;
+
+        loop {
+            let (offset, err) = self.decoder.raw_feed(&input[remaining..], output);
+            let unprocessed = remaining + offset;
+            match err {
+                Some(err) => {
+                    let problem_start = if unprocessed == 0 {
+                        // the problem may start in one of the previous chunks
+                        base.saturating_sub(self.unprocessed.len())
+                    } else {
+                        base + unprocessed
+                    };
+                    let upto = remaining as isize + err.upto;
+                    let keep_going = if upto < 0 {
+                        // the first remaining byte was fed in one of the previous chunks
+                        let start = (self.unprocessed.len() as isize + upto) as usize;
+                        let mut joined = self.unprocessed[start..].to_vec();
+                        joined.extend_from_slice(&input);
+                        base -= self.unprocessed.len() - start;
+                        input = Cow::Owned(joined);
+                        remaining = 0;
+                        self.trap.trap(&mut *self.decoder, &[], output)
+                    } else {
+                        remaining = upto as usize;
+                        let problem = &input[unprocessed.min(remaining)..remaining];
+                        self.trap.trap(&mut *self.decoder, problem, output)
+                    };
+                    if !keep_going {
+                        return Err(DecodeError {
+                            position: problem_start,
+                            cause: err.cause,
+                        });
+                    }
+                    self.unprocessed.clear();
+                }
+                None => {
+                    if offset == 0 && remaining == 0 {
+                        self.unprocessed.extend_from_slice(&input);
+                    } else {
+                        self.unprocessed = input[unprocessed..].to_vec();
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Signals the end of input, appending any remaining output to `output`.
+    pub fn finish(&mut self, output: &mut String) -> Result<(), DecodeError> {
+        while let Some(err) = self.decoder.raw_finish(output) {
+            let problem_start = self.position - self.unprocessed.len();
+            let start = (self.unprocessed.len() as isize + err.upto).max(0) as usize;
+            let rest = self
+                .unprocessed
+                .split_off(start.min(self.unprocessed.len()));
+            if !self
+                .trap
+                .trap(&mut *self.decoder, &self.unprocessed, output)
+            {
+                return Err(DecodeError {
+                    position: problem_start,
+                    cause: err.cause,
+                });
+            }
+            self.unprocessed.clear();
+            if !rest.is_empty() {
+                // fed again, so don't count them twice
+                self.position -= rest.len();
+                self.feed(&rest, output)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tells whether the encoding is UTF-16 or UTF-32.
+pub fn is_wide(encoding: EncodingRef) -> bool {
+    encoding.name().starts_with("utf-16") || encoding.name().starts_with("utf-32")
+}
+
+/// A writer accepting UTF-8 and passing it to the inner writer, encoded in given encoding.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] on characters which the encoding cannot represent.
+pub struct EncodingWriter<W: Write> {
+    inner: W,
+    encoding: EncodingRef,
+    encoder: Box<dyn RawEncoder>,
+    /// Incomplete UTF-8 sequence from the end of the previous write.
+    incomplete: Vec<u8>,
+    buffer: Vec<u8>,
+    written: usize,
+    /// Whether the input is passed through as it is, without checking that it is UTF-8.
+    raw: bool,
+}
+
+impl<W: Write> EncodingWriter<W> {
+    pub fn new(inner: W, encoding: EncodingRef) -> Self {
+        EncodingWriter {
+            inner,
+            encoding,
+            encoder: encoding.raw_encoder(),
+            incomplete: Vec::new(),
+            buffer: Vec::new(),
+            written: 0,
+            raw: false,
+        }
+    }
+
+    /// Returns a writer passing the input through as it is, for input in an unknown
+    /// ASCII-compatible encoding, which is written like UTF-8, but may not be valid.
+    pub fn raw(inner: W) -> Self {
+        EncodingWriter {
+            raw: true,
+            ..EncodingWriter::new(inner, UTF_8)
+        }
+    }
+
+    /// Writes the byte order mark of the output encoding, if it needs one.
+    pub fn write_bom(&mut self) -> io::Result<()> {
+        let bom: &[u8] = match self.encoding.name() {
+            "utf-8" => &[0xEF, 0xBB, 0xBF],
+            "utf-16le" => &[0xFF, 0xFE],
+            "utf-16be" => &[0xFE, 0xFF],
+            "utf-32le" => &[0xFF, 0xFE, 0x00, 0x00],
+            "utf-32be" => &[0x00, 0x00, 0xFE, 0xFF],
+            _ => &[],
+        };
+        self.inner.write_all(bom)
+    }
+
+    /// Flushes the inner writer and returns the number of encoded bytes written (excluding BOM).
+    pub fn finish(mut self) -> io::Result<usize> {
+        if !self.incomplete.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not end on a character boundary",
+            ));
+        }
+        self.buffer.clear();
+        if let Some(e) = self.encoder.raw_finish(&mut self.buffer) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e.cause));
+        }
+        self.inner.write_all(&self.buffer)?;
+        self.written += self.buffer.len();
+        self.inner.flush()?;
+
+        Ok(self.written)
+    }
+
+    fn encode(&mut self, input: &str) -> io::Result<()> {
+        if self.encoding.name() == "utf-8" {
+            self.inner.write_all(input.as_bytes())?;
+            self.written += input.len();
+            return Ok(());
+        }
+
+        self.buffer.clear();
+        let (valid_up_to, err) = self.encoder.raw_feed(input, &mut self.buffer);
+        if let Some(err) = err {
+            let c = input[valid_up_to..err.upto as usize]
+                .chars()
+                .next()
+                .unwrap_or(std::char::REPLACEMENT_CHARACTER);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "character {:?} (U+{:04X}) cannot be represented in {}",
+                    c,
+                    c as u32,
+                    self.encoding.name()
+                ),
+            ));
+        }
+        self.inner.write_all(&self.buffer)?;
+        self.written += self.buffer.len();
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.raw {
+            self.inner.write_all(buf)?;
+            self.written += buf.len();
+            return Ok(buf.len());
+        }
+
+        let joined;
+        let input = if self.incomplete.is_empty() {
+            buf
+        } else {
+            let mut incomplete = std::mem::take(&mut self.incomplete);
+            incomplete.extend_from_slice(buf);
+            joined = incomplete;
+            &joined
+        };
+
+        let valid = match std::str::from_utf8(input) {
+            Ok(s) => s,
+            Err(e) if e.error_len().is_none() => {
+                self.incomplete = input[e.valid_up_to()..].to_vec();
+                std::str::from_utf8(&input[..e.valid_up_to()])
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        self.encode(valid)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,164 @@
+//! UTF-32 codec, which the `encoding` crate lacks.
+
+use encoding::types::{ByteWriter, CodecError, RawDecoder, RawEncoder, StringWriter};
+use encoding::Encoding;
+
+pub struct Utf32Encoding {
+    big_endian: bool,
+}
+
+pub static UTF_32LE: Utf32Encoding = Utf32Encoding { big_endian: false };
+pub static UTF_32BE: Utf32Encoding = Utf32Encoding { big_endian: true };
+
+impl Encoding for Utf32Encoding {
+    fn name(&self) -> &'static str {
+        if self.big_endian {
+            "utf-32be"
+        } else {
+            "utf-32le"
+        }
+    }
+
+    fn raw_encoder(&self) -> Box<dyn RawEncoder> {
+        Box::new(Utf32Encoder {
+            big_endian: self.big_endian,
+        })
+    }
+
+    fn raw_decoder(&self) -> Box<dyn RawDecoder> {
+        Box::new(Utf32Decoder {
+            big_endian: self.big_endian,
+            unit: [0; 4],
+            len: 0,
+        })
+    }
+}
+
+struct Utf32Encoder {
+    big_endian: bool,
+}
+
+impl RawEncoder for Utf32Encoder {
+    fn from_self(&self) -> Box<dyn RawEncoder> {
+        Box::new(Utf32Encoder {
+            big_endian: self.big_endian,
+        })
+    }
+
+    fn raw_feed(
+        &mut self,
+        input: &str,
+        output: &mut dyn ByteWriter,
+    ) -> (usize, Option<CodecError>) {
+        output.writer_hint(input.len() * 4);
+        for c in input.chars() {
+            let c = c as u32;
+            if self.big_endian {
+                output.write_bytes(&c.to_be_bytes());
+            } else {
+                output.write_bytes(&c.to_le_bytes());
+            }
+        }
+
+        (input.len(), None)
+    }
+
+    fn raw_finish(&mut self, _output: &mut dyn ByteWriter) -> Option<CodecError> {
+        None
+    }
+}
+
+struct Utf32Decoder {
+    big_endian: bool,
+    /// Code unit split between calls to `raw_feed`.
+    unit: [u8; 4],
+    len: usize,
+}
+
+impl Utf32Decoder {
+    fn decode_unit(&self, unit: [u8; 4]) -> Option<char> {
+        let c = if self.big_endian {
+            u32::from_be_bytes(unit)
+        } else {
+            u32::from_le_bytes(unit)
+        };
+        std::char::from_u32(c)
+    }
+}
+
+impl RawDecoder for Utf32Decoder {
+    fn from_self(&self) -> Box<dyn RawDecoder> {
+        Box::new(Utf32Decoder {
+            big_endian: self.big_endian,
+            unit: [0; 4],
+            len: 0,
+        })
+    }
+
+    fn raw_feed(
+        &mut self,
+        input: &[u8],
+        output: &mut dyn StringWriter,
+    ) -> (usize, Option<CodecError>) {
+        output.writer_hint(input.len() / 4);
+        let mut i = 0;
+        let mut processed = 0;
+
+        if self.len > 0 {
+            while self.len < 4 && i < input.len() {
+                self.unit[self.len] = input[i];
+                self.len += 1;
+                i += 1;
+            }
+            if self.len < 4 {
+                return (0, None);
+            }
+            self.len = 0;
+            match self.decode_unit(self.unit) {
+                Some(c) => {
+                    output.write_char(c);
+                    processed = i;
+                }
+                None => return (0, Some(invalid_sequence(i))),
+            }
+        }
+
+        while i + 4 <= input.len() {
+            let unit = [input[i], input[i + 1], input[i + 2], input[i + 3]];
+            i += 4;
+            match self.decode_unit(unit) {
+                Some(c) => {
+                    output.write_char(c);
+                    processed = i;
+                }
+                None => return (processed, Some(invalid_sequence(i))),
+            }
+        }
+
+        for &b in &input[i..] {
+            self.unit[self.len] = b;
+            self.len += 1;
+        }
+
+        (processed, None)
+    }
+
+    fn raw_finish(&mut self, _output: &mut dyn StringWriter) -> Option<CodecError> {
+        if self.len > 0 {
+            self.len = 0;
+            return Some(CodecError {
+                upto: 0,
+                cause: "incomplete sequence".into(),
+            });
+        }
+
+        None
+    }
+}
+
+fn invalid_sequence(upto: usize) -> CodecError {
+    CodecError {
+        upto: upto as isize,
+        cause: "invalid sequence".into(),
+    }
+}
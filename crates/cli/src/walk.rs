@@ -0,0 +1,90 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
+
+/// Expands the `FILE` arguments into the list of files to process.
+pub struct FileWalker<'a> {
+    recursive: bool,
+    include: Vec<&'a str>,
+    exclude: Vec<&'a str>,
+    gitignore: bool,
+}
+
+impl<'a> FileWalker<'a> {
+    pub fn new(recursive: bool) -> Self {
+        FileWalker {
+            recursive,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            gitignore: false,
+        }
+    }
+
+    /// Only process files matching one of given globs, when walking directories.
+    pub fn include<I: IntoIterator<Item = &'a str>>(mut self, globs: I) -> Self {
+        self.include.extend(globs);
+        self
+    }
+
+    /// Skip files matching any of given globs, when walking directories.
+    pub fn exclude<I: IntoIterator<Item = &'a str>>(mut self, globs: I) -> Self {
+        self.exclude.extend(globs);
+        self
+    }
+
+    /// Skip files ignored by git and the `.git` directory, when walking directories.
+    pub fn gitignore(mut self, yes: bool) -> Self {
+        self.gitignore = yes;
+        self
+    }
+
+    /// Appends `path` to `files` if it is a file, or all files found in it, if it is a directory.
+    ///
+    /// Directories are only walked if the walker is recursive. Otherwise they're returned as they are,
+    /// and fail to open later.
+    ///
+    /// Returns the errors met while walking (like unreadable subdirectories), which don't stop
+    /// the walk, or an error if the walk could not start at all.
+    pub fn collect(
+        &self,
+        path: &Path,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<Vec<ignore::Error>, ignore::Error> {
+        let mut errors = Vec::new();
+        if !self.recursive || !path.is_dir() {
+            files.push(path.to_owned());
+            return Ok(errors);
+        }
+
+        let mut overrides = OverrideBuilder::new(path);
+        for glob in &self.include {
+            overrides.add(glob)?;
+        }
+        for glob in &self.exclude {
+            overrides.add(&format!("!{}", glob))?;
+        }
+
+        let gitignore = self.gitignore;
+        let walker = WalkBuilder::new(path)
+            .standard_filters(false)
+            .git_ignore(gitignore)
+            .git_exclude(gitignore)
+            .git_global(gitignore)
+            .require_git(false)
+            .overrides(overrides.build()?)
+            .filter_entry(move |e| !(gitignore && e.file_name() == ".git"))
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walker {
+            match entry {
+                Ok(entry) if entry.file_type().map(|t| t.is_file()) == Some(true) => {
+                    files.push(entry.into_path())
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        Ok(errors)
+    }
+}
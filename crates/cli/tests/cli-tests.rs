@@ -2,6 +2,7 @@ use assert_cmd::prelude::*; // Add methods on commands
 use predicates::prelude::*; // Used for writing assertions
 use std::fs;
 use std::io::Write;
+use std::process::Stdio;
 use tempfile::NamedTempFile;
 
 #[test]
@@ -13,9 +14,29 @@ fn file_doesnt_exist() -> Result<(), Box<dyn std::error::Error>> {
         .run()?;
     let mut cmd = bin.command();
     cmd.arg("--dos2unix").arg("test/file/doesnt/exist");
+    cmd.assert().code(1).stderr(predicate::str::starts_with(
+        "error: test/file/doesnt/exist: could not read: ",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn usage_error() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--unix2dos");
+    cmd.assert().code(2);
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("-o").arg("out").arg("a").arg("b");
     cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("kind: NotFound"));
+        .code(2)
+        .stderr("error: OUT cannot be used with multiple FILEs\n");
 
     Ok(())
 }
@@ -144,3 +165,1044 @@ fn binary_force() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn stdin_dos2unix() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix");
+    cmd.with_stdin()
+        .buffer("foo\r\nbar\r\n")
+        .assert()
+        .success()
+        .stdout("foo\nbar\n");
+
+    Ok(())
+}
+
+#[test]
+fn large_file_dos2unix() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    // odd line length, so that `\r\n` pairs get split between chunks
+    let line = "Zażółć gęślą jaźń\r\n";
+    for _ in 0..20_000 {
+        file.write_all(line.as_bytes())?;
+    }
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(file.path());
+    cmd.assert().success();
+    let converted = fs::read(file)?;
+    assert_eq!(
+        converted,
+        line.replace("\r\n", "\n").repeat(20_000).as_bytes()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn large_file_utf16_unix2dos() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    let line: Vec<u8> = "ä\n"
+        .encode_utf16()
+        .flat_map(|x| x.to_le_bytes().to_vec())
+        .collect();
+    file.write_all(b"\xff\xfe")?;
+    for _ in 0..50_000 {
+        file.write_all(&line)?;
+    }
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg(file.path());
+    cmd.assert().success();
+    let converted = fs::read(file)?;
+    let expected_line: Vec<u8> = "ä\r\n"
+        .encode_utf16()
+        .flat_map(|x| x.to_le_bytes().to_vec())
+        .collect();
+    let mut expected = b"\xff\xfe".to_vec();
+    for _ in 0..50_000 {
+        expected.extend_from_slice(&expected_line);
+    }
+    assert_eq!(converted, expected);
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_mac2unix() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo\rbar\r\nbaz\r")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--mac2unix").arg(file.path());
+    cmd.assert().success();
+    let converted = fs::read(file)?;
+    assert_eq!(converted, b"foo\nbar\r\nbaz\n");
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_unix2mac() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo\nbar\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--unix2mac").arg(file.path());
+    cmd.assert().success();
+    let converted = fs::read(file)?;
+    assert_eq!(converted, b"foo\rbar\r");
+
+    Ok(())
+}
+
+#[test]
+fn multiple_modes() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--mac2unix");
+    cmd.stdin(Stdio::null());
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_to_crlf() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo\nbar\rbaz\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--to").arg("crlf").arg(file.path());
+    cmd.assert().success();
+    let converted = fs::read(file)?;
+    assert_eq!(converted, b"foo\r\nbar\r\nbaz\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn check() -> Result<(), Box<dyn std::error::Error>> {
+    let mut converted = NamedTempFile::new()?;
+    write!(converted, "foo\nbar\n")?;
+    let mut not_converted = NamedTempFile::new()?;
+    write!(not_converted, "foo\r\nbar\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--check")
+        .arg(converted.path())
+        .arg(not_converted.path());
+    cmd.assert()
+        .code(3)
+        .stdout(format!("{}\n", not_converted.path().display()));
+    assert_eq!(fs::read(not_converted)?, b"foo\r\nbar\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--check").arg(converted.path());
+    cmd.assert().success().stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn info_table() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    // `\r\n` split between two chunks of input
+    file.write_all(&vec![b'a'; 64 * 1024 - 1])?;
+    write!(file, "\r\nfoo\nbar\rbaz\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--info").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "      LF     CRLF       CR  BOM  ENCODING  BINARY  FILE\n       1        2        1  no   utf-8     no      {}\n",
+        file.path().display()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn info_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"\xff\xfea\x00\r\x00\n\x00b\x00\n\x00")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--info=json").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "{{\"file\":\"{}\",\"lf\":1,\"crlf\":1,\"cr\":0,\"bom\":true,\"encoding\":\"utf-16le\",\"binary\":false}}\n",
+        file.path().display()
+    ));
+    let unchanged = fs::read(file)?;
+    assert_eq!(unchanged, b"\xff\xfea\x00\r\x00\n\x00b\x00\n\x00");
+
+    Ok(())
+}
+
+#[test]
+fn info_unknown_encoding() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"caf\xe9\r\nbar\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--info=json").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "{{\"file\":\"{}\",\"lf\":1,\"crlf\":1,\"cr\":0,\"bom\":false,\"encoding\":\"unknown\",\"binary\":false}}\n",
+        file.path().display()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn recursive() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("src/sub"))?;
+    fs::create_dir(dir.path().join("target"))?;
+    fs::write(dir.path().join(".gitignore"), "target\n")?;
+    fs::write(dir.path().join("src/a.rs"), "foo\r\n")?;
+    fs::write(dir.path().join("src/a.txt"), "foo\r\n")?;
+    fs::write(dir.path().join("src/bin.rs"), b"\x00\x01\r\n")?;
+    fs::write(dir.path().join("src/sub/b.rs"), "foo\r\n")?;
+    fs::write(dir.path().join("target/c.rs"), "foo\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("-r")
+        .arg("--include=*.rs")
+        .arg("--exclude=sub")
+        .arg("--gitignore")
+        .arg(dir.path());
+    cmd.assert().success();
+
+    assert_eq!(fs::read(dir.path().join("src/a.rs"))?, b"foo\n");
+    assert_eq!(fs::read(dir.path().join("src/a.txt"))?, b"foo\r\n");
+    assert_eq!(fs::read(dir.path().join("src/bin.rs"))?, b"\x00\x01\r\n");
+    assert_eq!(fs::read(dir.path().join("src/sub/b.rs"))?, b"foo\r\n");
+    assert_eq!(fs::read(dir.path().join("target/c.rs"))?, b"foo\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn directory_without_recursive() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(dir.path());
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_replaces_atomically() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("script.sh");
+    fs::write(&path, "#!/bin/sh\r\necho foo\r\n")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o751))?;
+    }
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(&path);
+    cmd.assert().success();
+
+    assert_eq!(fs::read(&path)?, b"#!/bin/sh\necho foo\n");
+    // no temporary files left behind
+    assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o751);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_keep_date() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo\r\nbar\r\n")?;
+    let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(file.path(), mtime)?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("-k").arg(file.path());
+    cmd.assert().success();
+
+    let metadata = fs::metadata(file.path())?;
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&metadata),
+        mtime
+    );
+    assert_eq!(fs::read(file)?, b"foo\nbar\n");
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_backup() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("foo.txt");
+    fs::write(&path, "foo\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--backup").arg(&path);
+    cmd.assert().success();
+    assert_eq!(fs::read(&path)?, b"foo\n");
+    assert_eq!(fs::read(dir.path().join("foo.txt.orig"))?, b"foo\r\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg("--backup=.bak").arg(&path);
+    cmd.assert().success();
+    assert_eq!(fs::read(&path)?, b"foo\r\n");
+    assert_eq!(fs::read(dir.path().join("foo.txt.bak"))?, b"foo\n");
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_numbered_backup() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("foo.txt");
+    fs::write(&path, "foo\r\n")?;
+    fs::write(dir.path().join("foo.txt.~2~"), "old")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--backup=numbered").arg(&path);
+    cmd.assert().success();
+    assert_eq!(fs::read(&path)?, b"foo\n");
+    assert_eq!(fs::read(dir.path().join("foo.txt.~2~"))?, b"old");
+    assert_eq!(fs::read(dir.path().join("foo.txt.~3~"))?, b"foo\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn file_in_place_unchanged() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("foo.txt");
+    fs::write(&path, "foo\nbar\n")?;
+    let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&path, mtime)?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--backup").arg(&path);
+    cmd.assert().success();
+
+    // neither backed up nor rewritten
+    assert!(!dir.path().join("foo.txt.orig").exists());
+    let metadata = fs::metadata(&path)?;
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&metadata),
+        mtime
+    );
+    assert_eq!(fs::read(&path)?, b"foo\nbar\n");
+
+    Ok(())
+}
+
+#[test]
+fn dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo\r\nbar\nbaz\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--dry-run").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "{}\n       1: ␍␊ → ␊\n       3: ␍␊ → ␊\n",
+        file.path().display()
+    ));
+
+    // other edits to the text would not be reported
+    for option in &[
+        "--add-eol",
+        "--trim-trailing-newlines",
+        "--strip-trailing-whitespace",
+    ] {
+        let mut cmd = bin.command();
+        cmd.arg("--dos2unix")
+            .arg(option)
+            .arg("--dry-run")
+            .arg(file.path());
+        cmd.assert().code(2).stdout("");
+    }
+    assert_eq!(fs::read(file)?, b"foo\r\nbar\nbaz\r\n");
+
+    // a lone `\r` does not end a line for --dos2unix, but does for --mac2unix
+    let mut file = NamedTempFile::new()?;
+    write!(file, "a\rb\r\nc\r\n")?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--dry-run").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "{}\n       1: ␍␊ → ␊\n       2: ␍␊ → ␊\n",
+        file.path().display()
+    ));
+    let mut cmd = bin.command();
+    cmd.arg("--mac2unix").arg("--dry-run").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n       1: ␍ → ␊\n", file.path().display()));
+
+    Ok(())
+}
+
+#[test]
+fn latin1_without_bom() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"caf\xe9\r\nna\xefve\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(fs::read(file.path())?, b"caf\xe9\nna\xefve\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(fs::read(file.path())?, b"caf\xe9\r\nna\xefve\r\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix");
+    cmd.with_stdin()
+        .buffer(&b"caf\xe9\r\n"[..])
+        .assert()
+        .success()
+        .stdout(&b"caf\xe9\n"[..]);
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--dry-run").arg(file.path());
+    cmd.assert().success().stdout(format!(
+        "{}\n       1: ␍␊ → ␊\n       2: ␍␊ → ␊\n",
+        file.path().display()
+    ));
+
+    // the encoding has to be known to validate or re-encode the input
+    for option in &["--strict", "--encode=utf-16le"] {
+        let mut cmd = bin.command();
+        cmd.arg("--dos2unix").arg(option).arg(file.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("--from-encoding"));
+        assert_eq!(fs::read(file.path())?, b"caf\xe9\r\nna\xefve\r\n");
+    }
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--from-encoding=windows-1252")
+        .arg(file.path());
+    cmd.assert().success();
+    assert_eq!(fs::read(file.path())?, b"caf\xe9\nna\xefve\n");
+
+    Ok(())
+}
+
+#[test]
+fn utf16_without_bom() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"\x00f\x00o\x00o\x00\r\x00\n\x00b\x00a\x00r\x00\r\x00\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    // zero bytes look binary, unless the encoding is known
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(
+        fs::read(file.path())?,
+        b"\x00f\x00o\x00o\x00\r\x00\n\x00b\x00a\x00r\x00\r\x00\n"
+    );
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--force").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(
+        fs::read(file.path())?,
+        b"\x00f\x00o\x00o\x00\n\x00b\x00a\x00r\x00\n"
+    );
+
+    // a guessed encoding is not trusted with invalid input
+    let invalid = b"\x00a\x00b\x00c\x00d\x00e\x00f\x00g\x00h\xdc\x00\x00\n";
+    let mut file = NamedTempFile::new()?;
+    file.write_all(invalid)?;
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg("--force").arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("guessed to be utf-16be"));
+    assert_eq!(fs::read(file.path())?, invalid);
+
+    Ok(())
+}
+
+#[test]
+fn binary_wide_values_without_bom() -> Result<(), Box<dyn std::error::Error>> {
+    // little-endian 16-bit numbers, which could pass for UTF-16 by the zero bytes in them
+    let data: Vec<u8> = [1u16, 2, 10, 3, 200, 10]
+        .iter()
+        .cycle()
+        .take(240)
+        .flat_map(|x| x.to_le_bytes().to_vec())
+        .collect();
+    let mut file = NamedTempFile::new()?;
+    file.write_all(&data)?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(fs::read(file.path())?, data);
+
+    Ok(())
+}
+
+#[test]
+fn utf32_with_bom() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"\xff\xfe\x00\x00a\x00\x00\x00\n\x00\x00\x00")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--unix2dos").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(
+        fs::read(file.path())?,
+        &b"\xff\xfe\x00\x00a\x00\x00\x00\r\x00\x00\x00\n\x00\x00\x00"[..]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn legacy_output_encodings() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    for (label, expected) in &[
+        ("windows-1250", &b"\x8a\r\n"[..]),
+        ("iso-8859-2", &b"\xa9\r\n"[..]),
+        ("shift_jis", &b"\x83V\r\n"[..]),
+        (
+            "utf-32le",
+            &b"\xff\xfe\x00\x00`\x01\x00\x00\r\x00\x00\x00\n\x00\x00\x00"[..],
+        ),
+    ] {
+        let mut cmd = bin.command();
+        cmd.arg("--unix2dos").arg("-e").arg(label);
+        let input = if *label == "shift_jis" {
+            "シ\n"
+        } else {
+            "Š\n"
+        };
+        cmd.with_stdin()
+            .buffer(input)
+            .assert()
+            .success()
+            .stdout(*expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn unrepresentable_character() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "100 €\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("-e")
+        .arg("iso-8859-2")
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("U+20AC"));
+    assert_eq!(fs::read(file.path())?, "100 €\r\n".as_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn bom_modes() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    for (args, input, expected) in &[
+        (&[][..], &b"\xef\xbb\xbfa\r\n"[..], &b"\xef\xbb\xbfa\n"[..]),
+        (&["--bom", "keep"][..], b"a\r\n", b"a\n"),
+        (&["--bom", "remove"][..], b"\xef\xbb\xbfa\r\n", b"a\n"),
+        (&["--bom", "add"][..], b"a\r\n", b"\xef\xbb\xbfa\n"),
+        (
+            &["--bom", "add", "-e", "utf-16be"][..],
+            b"a\r\n",
+            b"\xfe\xff\0a\0\n",
+        ),
+        (
+            &["--bom", "remove", "-e", "utf-16be"][..],
+            b"a\r\n",
+            b"\0a\0\n",
+        ),
+        (&["--bom", "remove"][..], b"\xff\xfea\0\r\0\n\0", b"a\0\n\0"),
+    ] {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(input)?;
+        let mut cmd = bin.command();
+        cmd.arg("--dos2unix").args(*args).arg(file.path());
+        cmd.assert().success();
+        assert_eq!(fs::read(file.path())?, *expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn strict() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"\xef\xbb\xbffoo\r\nb\xffr\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--strict").arg(file.path());
+    cmd.assert().failure().stderr(format!(
+        "error: {}: invalid sequence at byte 9\n",
+        file.path().display()
+    ));
+    assert_eq!(fs::read(file.path())?, b"\xef\xbb\xbffoo\r\nb\xffr\r\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(file.path());
+    cmd.assert().success();
+    assert_eq!(
+        fs::read(file.path())?,
+        "\u{feff}foo\nb\u{fffd}r\n".as_bytes()
+    );
+
+    // errors name the file as it was given
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("s.txt"), b"b\xffr\r\n")?;
+    let mut cmd = bin.command();
+    cmd.current_dir(dir.path())
+        .arg("--dos2unix")
+        .arg("--strict")
+        .arg("s.txt");
+    cmd.assert().failure().stderr(predicate::str::starts_with(
+        "error: s.txt: invalid sequence",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn continue_after_error() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("first.txt");
+    let invalid = dir.path().join("invalid.txt");
+    let last = dir.path().join("last.txt");
+    fs::write(&first, "foo\r\n")?;
+    fs::write(&invalid, b"\xef\xbb\xbfb\xffr\r\n")?;
+    fs::write(&last, "bar\r\n")?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--fail-fast")
+        .arg("--strict")
+        .arg(&first)
+        .arg(&invalid)
+        .arg(&last);
+    cmd.assert().code(1).stderr(format!(
+        "error: {}: invalid sequence at byte 4\n",
+        invalid.display()
+    ));
+    assert_eq!(fs::read_to_string(&first)?, "foo\n");
+    assert_eq!(fs::read_to_string(&last)?, "bar\r\n");
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--strict")
+        .arg(&first)
+        .arg(&invalid)
+        .arg(&last);
+    cmd.assert().code(1).stderr(format!(
+        "error: {}: invalid sequence at byte 4\n2 files processed, 0 skipped, 1 failed\n",
+        invalid.display()
+    ));
+    assert_eq!(fs::read(&invalid)?, b"\xef\xbb\xbfb\xffr\r\n");
+    assert_eq!(fs::read_to_string(&last)?, "bar\n");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn continue_after_walk_error() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked)?;
+    fs::write(locked.join("hidden.txt"), "foo\r\n")?;
+    fs::write(dir.path().join("file.txt"), "bar\r\n")?;
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+    if fs::read_dir(&locked).is_ok() {
+        // permissions don't apply, when running as root
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+        return Ok(());
+    }
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--recursive").arg(dir.path());
+    let failed = predicate::str::ends_with("1 files processed, 0 skipped, 1 failed\n");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains(locked.display().to_string()).and(failed));
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+    assert_eq!(fs::read_to_string(dir.path().join("file.txt"))?, "bar\n");
+
+    Ok(())
+}
+
+#[test]
+fn jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut expected = String::new();
+    for i in 0..50 {
+        let path = dir.path().join(format!("{:02}.txt", i));
+        if i % 10 == 7 {
+            fs::write(&path, "foo\n")?;
+        } else {
+            fs::write(&path, "foo\r\n".repeat(i * 100 + 1))?;
+            expected.push_str(&format!("{}\n", path.display()));
+        }
+    }
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--check")
+        .arg("--jobs")
+        .arg("4")
+        .arg("--recursive")
+        .arg(dir.path());
+    cmd.assert().code(3).stdout(expected);
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("-j")
+        .arg("4")
+        .arg("-r")
+        .arg(dir.path());
+    cmd.assert().success();
+    for i in 0..50 {
+        let converted = fs::read_to_string(dir.path().join(format!("{:02}.txt", i)))?;
+        assert!(!converted.contains('\r'));
+    }
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("-j").arg("0").arg(dir.path());
+    cmd.assert().code(2);
+
+    Ok(())
+}
+
+#[test]
+fn large_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mapped = dir.path().join("mapped.txt");
+    let read = dir.path().join("read.txt");
+    let invalid = dir.path().join("invalid.txt");
+    let mut input = b"\xef\xbb\xbf".to_vec();
+    input.extend("caf\u{e9}\r\nbar\r\n".repeat(200_000).as_bytes());
+    fs::write(&mapped, &input)?;
+    fs::write(&read, &input)?;
+    input[2_000_000] = 0xFF;
+    fs::write(&invalid, &input)?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg(&mapped);
+    cmd.assert().success();
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--no-mmap").arg(&read);
+    cmd.assert().success();
+    let expected = format!("\u{feff}{}", "caf\u{e9}\nbar\n".repeat(200_000));
+    assert_eq!(fs::read_to_string(&mapped)?, expected);
+    assert_eq!(fs::read_to_string(&read)?, expected);
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--strict").arg(&invalid);
+    cmd.assert().failure().stderr(format!(
+        "error: {}: invalid sequence at byte 2000000\n",
+        invalid.display()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn verbose_counts_line_breaks() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all("foo\r\n".repeat(1204).as_bytes())?;
+
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix").arg("--verbose").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "{}: converted 1,204 line breaks. 6020 bytes read. 4816 bytes written",
+            file.path().display()
+        )));
+
+    Ok(())
+}
+
+#[test]
+fn final_newline() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let cases: &[(&str, &[&str], &str)] = &[
+        ("foo\nbar", &["--unix2dos", "--add-eol"], "foo\r\nbar\r\n"),
+        ("foo\nbar\n", &["--unix2dos", "--add-eol"], "foo\r\nbar\r\n"),
+        ("", &["--unix2dos", "--add-eol"], ""),
+        (
+            "foo\r\n\r\nbar\r\n\r\n\n",
+            &["--dos2unix", "--trim-trailing-newlines"],
+            "foo\n\nbar\n",
+        ),
+        ("foo", &["--dos2unix", "--trim-trailing-newlines"], "foo"),
+        (
+            "foo\nbar",
+            &["--to=crlf", "--trim-trailing-newlines", "--add-eol"],
+            "foo\r\nbar\r\n",
+        ),
+        (
+            "foo\rbar\r\r\r",
+            &["--to=crlf", "--trim-trailing-newlines", "--add-eol"],
+            "foo\r\nbar\r\n",
+        ),
+    ];
+    for (input, args, expected) in cases {
+        let mut cmd = bin.command();
+        cmd.args(*args)
+            .with_stdin()
+            .buffer(*input)
+            .assert()
+            .success()
+            .stdout(*expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn strip_trailing_whitespace() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = escargot::CargoBuild::new()
+        .bin("rnc")
+        .current_release()
+        .current_target()
+        .run()?;
+    let cases: &[(&str, &[&str], &str)] = &[
+        (
+            "foo \r\nbar\t\t\r\n baz\r\n",
+            &["--dos2unix", "--strip-trailing-whitespace"],
+            "foo\nbar\n baz\n",
+        ),
+        (
+            "foo \nbar \t",
+            &["--unix2dos", "--strip-trailing-whitespace"],
+            "foo\r\nbar",
+        ),
+        (
+            "foo\t\n \n\t\n",
+            &[
+                "--to=crlf",
+                "--strip-trailing-whitespace",
+                "--trim-trailing-newlines",
+            ],
+            "foo\r\n",
+        ),
+        ("foo \n", &["--dos2unix"], "foo \n"),
+    ];
+    for (input, args, expected) in cases {
+        let mut cmd = bin.command();
+        cmd.args(*args)
+            .with_stdin()
+            .buffer(*input)
+            .assert()
+            .success()
+            .stdout(*expected);
+    }
+
+    // line endings already converted, but whitespace to strip
+    let mut file = NamedTempFile::new()?;
+    write!(file, "foo \nbar\n")?;
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--strip-trailing-whitespace")
+        .arg("--check")
+        .arg(file.path());
+    cmd.assert()
+        .code(3)
+        .stdout(format!("{}\n", file.path().display()));
+
+    let mut cmd = bin.command();
+    cmd.arg("--dos2unix")
+        .arg("--strip-trailing-whitespace")
+        .arg(file.path());
+    cmd.assert().success();
+    assert_eq!(fs::read(file.path())?, b"foo\nbar\n");
+
+    Ok(())
+}
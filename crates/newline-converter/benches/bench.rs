@@ -1,8 +1,14 @@
 use std::borrow::Cow;
+use std::io::Write;
 
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use lazy_regex::{lazy_regex, Lazy};
-use newline_converter::{dos2unix, unix2dos};
+use newline_converter::{
+    analyze, dos2unix, unix2dos, Dos2UnixWriter, LineEnding, NormalizeWriter, Unix2DosWriter,
+};
+
+mod common;
+use common::large_input;
 
 fn dos2unix_string_replace<T: AsRef<str> + ?Sized>(input: &T) -> String {
     input.as_ref().replace("\r\n", "\n")
@@ -17,15 +23,15 @@ static RE_UNIX: lazy_regex::Lazy<lazy_regex::Regex> = lazy_regex!("\n");
 static RE_UNIX_FANCY: Lazy<fancy_regex::Regex> =
     Lazy::new(|| fancy_regex::Regex::new("(?!\r)\n").unwrap());
 
-fn dos2unix_regex<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
+fn dos2unix_regex<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
     RE_DOS.replace_all(input.as_ref(), "\n")
 }
 
-fn unix2dos_regex<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
+fn unix2dos_regex<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
     RE_UNIX.replace_all(input.as_ref(), "\r\n")
 }
 
-fn unix2dos_regex_fancy<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
+fn unix2dos_regex_fancy<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
     RE_UNIX_FANCY.replace_all(input.as_ref(), "\r\n")
 }
 
@@ -98,11 +104,91 @@ fn bench_unix2dos_noop(c: &mut Criterion) {
     group.finish();
 }
 
+/// Writes `input` to `writer` in chunks, the way a file is streamed through it.
+fn write_chunked<W: Write>(writer: &mut W, input: &str) {
+    for chunk in input.as_bytes().chunks(64 * 1024) {
+        writer.write_all(chunk).unwrap();
+    }
+}
+
+fn dos2unix_writer(input: &str) -> Vec<u8> {
+    let mut writer = Dos2UnixWriter::new(Vec::with_capacity(input.len()));
+    write_chunked(&mut writer, input);
+    writer.finish().unwrap()
+}
+
+fn unix2dos_writer(input: &str) -> Vec<u8> {
+    let mut writer = Unix2DosWriter::new(Vec::with_capacity(input.len() * 2));
+    write_chunked(&mut writer, input);
+    writer.finish().unwrap()
+}
+
+/// The generic streaming conversion, for comparison with the dedicated writers.
+fn normalize_writer(input: &str, target: LineEnding) -> Vec<u8> {
+    let mut writer = NormalizeWriter::new(Vec::with_capacity(input.len() * 2), target);
+    write_chunked(&mut writer, input);
+    writer.finish().unwrap()
+}
+
+fn bench_large(c: &mut Criterion) {
+    let dos = large_input(4 << 20, "\r\n");
+    let unix = large_input(4 << 20, "\n");
+
+    let mut group = c.benchmark_group("dos2unix_large");
+    group.throughput(Throughput::Bytes(dos.len() as u64));
+    group.bench_with_input(BenchmarkId::new("newline-converter", ""), &dos, |b, i| {
+        b.iter(|| dos2unix(i))
+    });
+    group.bench_with_input(BenchmarkId::new("string.replace", ""), &dos, |b, i| {
+        b.iter(|| dos2unix_string_replace(i))
+    });
+    group.bench_with_input(BenchmarkId::new("regex", ""), &dos, |b, i| {
+        b.iter(|| dos2unix_regex(i))
+    });
+    group.bench_with_input(BenchmarkId::new("Dos2UnixWriter", ""), &dos, |b, i| {
+        b.iter(|| dos2unix_writer(i))
+    });
+    group.bench_with_input(BenchmarkId::new("NormalizeWriter", ""), &dos, |b, i| {
+        b.iter(|| normalize_writer(i, LineEnding::Lf))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("unix2dos_large");
+    group.throughput(Throughput::Bytes(unix.len() as u64));
+    group.bench_with_input(BenchmarkId::new("newline-converter", ""), &unix, |b, i| {
+        b.iter(|| unix2dos(i))
+    });
+    group.bench_with_input(BenchmarkId::new("string.replace", ""), &unix, |b, i| {
+        b.iter(|| unix2dos_string_replace(i))
+    });
+    group.bench_with_input(BenchmarkId::new("regex", ""), &unix, |b, i| {
+        b.iter(|| unix2dos_regex(i))
+    });
+    group.bench_with_input(BenchmarkId::new("Unix2DosWriter", ""), &unix, |b, i| {
+        b.iter(|| unix2dos_writer(i))
+    });
+    group.bench_with_input(BenchmarkId::new("NormalizeWriter", ""), &unix, |b, i| {
+        b.iter(|| normalize_writer(i, LineEnding::CrLf))
+    });
+    group.finish();
+}
+
+fn bench_analyze(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyze");
+    let i = DOS_INPUT;
+    group.bench_with_input(BenchmarkId::new("newline-converter", ""), i, |b, i| {
+        b.iter(|| analyze(i))
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_dos2unix,
     bench_dos2unix_noop,
     bench_unix2dos,
-    bench_unix2dos_noop
+    bench_unix2dos_noop,
+    bench_large,
+    bench_analyze
 );
 criterion_main!(benches);
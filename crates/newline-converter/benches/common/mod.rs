@@ -0,0 +1,26 @@
+//! Helpers shared by the benchmarks.
+
+/// Builds about `size` bytes of text with lines of various lengths, ending with `ending`.
+pub fn large_input(size: usize, ending: &str) -> String {
+    let words = [
+        "lorem",
+        "ipsum",
+        "dolor",
+        "sit",
+        "amet,",
+        "consectetur",
+        "adipiscing",
+        "elit",
+    ];
+    let mut input = String::with_capacity(size + 100);
+    let mut i = 0;
+    while input.len() < size {
+        for word in words.iter().cycle().skip(i % words.len()).take(i % 13 + 1) {
+            input.push_str(word);
+            input.push(' ');
+        }
+        input.push_str(ending);
+        i += 1;
+    }
+    input
+}
@@ -0,0 +1,116 @@
+//! Converting a large file the ways `rnc` can read it: whole into memory (like it used to),
+//! mapped into memory (like it does for large files) and in chunks (like it does with `--no-mmap`).
+//!
+//! This is a separate benchmark, because the allocator measuring the peak heap usage slows down
+//! every allocation, which would skew the other benchmarks.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use memmap2::Mmap;
+use newline_converter::{dos2unix, Dos2UnixWriter};
+
+mod common;
+use common::large_input;
+
+/// Allocator keeping track of the peak heap usage, to compare memory needs of the approaches.
+struct PeakAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAlloc = PeakAlloc;
+
+/// Returns how much more heap `f` needed at its peak, than was allocated before.
+fn peak_heap<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    f();
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+/// The file read whole, decoded, converted and written out again.
+fn read_whole(path: &Path) {
+    let text = String::from_utf8(fs::read(path).unwrap()).unwrap();
+    let converted = dos2unix(&text);
+    io::sink().write_all(converted.as_bytes()).unwrap();
+}
+
+/// The file mapped into memory, validated and converted right into the buffered output.
+fn mapped(path: &Path) {
+    let file = fs::File::open(path).unwrap();
+    // Safety: the file is not modified while the benchmark runs.
+    let map = unsafe { Mmap::map(&file) }.unwrap();
+    let text = std::str::from_utf8(&map).unwrap();
+    let mut writer = Dos2UnixWriter::new(io::BufWriter::new(io::sink()));
+    writer.write_all(text.as_bytes()).unwrap();
+    writer.finish().unwrap();
+}
+
+/// The file read in chunks, each converted into the buffered output.
+fn chunked(path: &Path) {
+    let mut file = fs::File::open(path).unwrap();
+    let mut writer = Dos2UnixWriter::new(io::BufWriter::new(io::sink()));
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer).unwrap() {
+            0 => break,
+            n => writer.write_all(&buffer[..n]).unwrap(),
+        }
+    }
+    writer.finish().unwrap();
+}
+
+fn bench_large_file(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("newline-converter-bench-{}", std::process::id()));
+    fs::write(&path, large_input(16 << 20, "\r\n")).unwrap();
+    let size = fs::metadata(&path).unwrap().len();
+
+    println!(
+        "large_file: {} KiB input, peak heap {} KiB read whole, {} KiB mapped, {} KiB chunked",
+        size / 1024,
+        peak_heap(|| read_whole(&path)) / 1024,
+        peak_heap(|| mapped(&path)) / 1024,
+        peak_heap(|| chunked(&path)) / 1024
+    );
+
+    let mut group = c.benchmark_group("large_file");
+    group.throughput(Throughput::Bytes(size));
+    group.sample_size(20);
+    group.bench_with_input(BenchmarkId::new("read_whole", ""), &path, |b, p| {
+        b.iter(|| read_whole(p))
+    });
+    group.bench_with_input(BenchmarkId::new("mapped", ""), &path, |b, p| {
+        b.iter(|| mapped(p))
+    });
+    group.bench_with_input(BenchmarkId::new("chunked", ""), &path, |b, p| {
+        b.iter(|| chunked(p))
+    });
+    group.finish();
+
+    fs::remove_file(&path).unwrap();
+}
+
+criterion_group!(benches, bench_large_file);
+criterion_main!(benches);
@@ -0,0 +1,179 @@
+//! Conversion builder, for options going beyond replacing line endings.
+
+use crate::final_newline::FinalNewline;
+use crate::rules::{Rules, DOS2MAC, DOS2UNIX, MAC2DOS, MAC2UNIX, UNIX2DOS, UNIX2MAC};
+use crate::stream::{ConverterReader, ConverterWriter};
+use crate::LineEnding;
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+/// A configurable conversion, for doing more in the same pass over the input
+/// than the conversion functions do.
+///
+/// Start with the constructor of the conversion wanted, then set the options.
+/// The conversion may then be applied to text in memory, or to streams.
+///
+/// # Examples
+/// ```
+/// use newline_converter::Converter;
+///
+/// let converter = Converter::dos2unix().strip_trailing_whitespace(true);
+/// assert_eq!(converter.convert("foo \r\nbar\t\r\nbaz  "), "foo\nbar\nbaz");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Converter {
+    rules: Rules,
+    /// Style of the line endings the conversion produces.
+    target: LineEnding,
+    final_newline: FinalNewline,
+}
+
+impl Converter {
+    /// Conversion of DOS-style line endings to UNIX-style, like [`dos2unix`](crate::dos2unix).
+    pub fn dos2unix() -> Self {
+        Converter::new(DOS2UNIX, LineEnding::Lf)
+    }
+
+    /// Conversion of UNIX-style line endings to DOS-style, like [`unix2dos`](crate::unix2dos).
+    pub fn unix2dos() -> Self {
+        Converter::new(UNIX2DOS, LineEnding::CrLf)
+    }
+
+    /// Conversion of classic Mac OS line endings to UNIX-style, like [`mac2unix`](crate::mac2unix).
+    pub fn mac2unix() -> Self {
+        Converter::new(MAC2UNIX, LineEnding::Lf)
+    }
+
+    /// Conversion of UNIX-style line endings to classic Mac OS, like [`unix2mac`](crate::unix2mac).
+    pub fn unix2mac() -> Self {
+        Converter::new(UNIX2MAC, LineEnding::Cr)
+    }
+
+    /// Conversion of classic Mac OS line endings to DOS-style, like [`mac2dos`](crate::mac2dos).
+    pub fn mac2dos() -> Self {
+        Converter::new(MAC2DOS, LineEnding::CrLf)
+    }
+
+    /// Conversion of DOS-style line endings to classic Mac OS, like [`dos2mac`](crate::dos2mac).
+    pub fn dos2mac() -> Self {
+        Converter::new(DOS2MAC, LineEnding::Cr)
+    }
+
+    /// Conversion of all line endings to the `target` style, like [`normalize`](crate::normalize).
+    pub fn normalize(target: LineEnding) -> Self {
+        Converter::new(Rules::normalize(target), target)
+    }
+
+    fn new(rules: Rules, target: LineEnding) -> Self {
+        Converter {
+            rules,
+            target,
+            final_newline: FinalNewline {
+                unicode: rules.unicode.is_some(),
+                ..FinalNewline::default()
+            },
+        }
+    }
+
+    /// Sets whether to remove spaces and tabs at the end of each line, including the last one.
+    /// Off by default.
+    pub fn strip_trailing_whitespace(mut self, strip: bool) -> Self {
+        self.rules.strip_trailing_whitespace = strip;
+        self
+    }
+
+    /// Sets whether to add a line ending in the target style at the end of the text, unless
+    /// it already ends with one, like [`add_final_newline`](crate::add_final_newline).
+    /// Off by default.
+    ///
+    /// The line endings recognized at the end are the ones the conversion recognizes,
+    /// so with [`normalize`](Self::normalize) also NEL, LS and PS.
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::Converter;
+    ///
+    /// let converter = Converter::unix2dos().add_final_newline(true);
+    /// assert_eq!(converter.convert("foo\nbar"), "foo\r\nbar\r\n");
+    /// ```
+    pub fn add_final_newline(mut self, add: bool) -> Self {
+        self.final_newline.add = if add { Some(self.target) } else { None };
+        self
+    }
+
+    /// Sets whether to remove blank lines at the end of the text, like
+    /// [`trim_trailing_newlines`](crate::trim_trailing_newlines). Off by default.
+    ///
+    /// Along with [`add_final_newline`](Self::add_final_newline), the text is made to end
+    /// with exactly one line ending, unless it is empty or made of line breaks only.
+    /// With [`normalize`](Self::normalize), NEL, LS and PS are removed too.
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::Converter;
+    ///
+    /// let converter = Converter::dos2unix().trim_trailing_newlines(true);
+    /// assert_eq!(converter.convert("foo\r\n\r\n\r\n"), "foo\n");
+    /// ```
+    pub fn trim_trailing_newlines(mut self, trim: bool) -> Self {
+        self.final_newline.trim = trim;
+        self
+    }
+
+    /// Converts `input`.
+    ///
+    /// The input string may already be in correct format, so this function
+    /// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::Converter;
+    ///
+    /// let converter = Converter::unix2dos().strip_trailing_whitespace(true);
+    /// assert_eq!(converter.convert("foo \nbar"), "foo\r\nbar");
+    /// ```
+    pub fn convert<'a, T: AsRef<str> + ?Sized>(&self, input: &'a T) -> Cow<'a, str> {
+        let output = match self.rules {
+            DOS2UNIX => crate::dos2unix(input),
+            UNIX2DOS => crate::unix2dos(input),
+            rules => rules.convert_str(input.as_ref()),
+        };
+        self.final_newline.apply_str(output)
+    }
+
+    /// Converts `input`, which does not have to be valid UTF-8. Unicode line endings
+    /// are only recognized in UTF-8, and the rest in any ASCII-compatible encoding.
+    ///
+    /// The input may already be in correct format, so this function
+    /// returns `Cow<[u8]>`, to avoid unnecessary allocation and copying.
+    pub fn convert_bytes<'a, T: AsRef<[u8]> + ?Sized>(&self, input: &'a T) -> Cow<'a, [u8]> {
+        let output = match self.rules {
+            DOS2UNIX => crate::dos2unix_bytes(input),
+            UNIX2DOS => crate::unix2dos_bytes(input),
+            rules => rules.convert_bytes(input.as_ref()),
+        };
+        self.final_newline.apply_bytes(output)
+    }
+
+    /// Returns a writer converting the data written to it, and writing it to `inner`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Converter;
+    ///
+    /// let mut writer = Converter::dos2unix().strip_trailing_whitespace(true).writer(Vec::new());
+    /// writer.write_all(b"foo \r")?;
+    /// writer.write_all(b"\nbar\t")?;
+    /// assert_eq!(writer.finish()?, b"foo\nbar");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn writer<W: Write>(&self, inner: W) -> ConverterWriter<W> {
+        ConverterWriter::new(inner, self.rules, self.final_newline)
+    }
+
+    /// Returns a reader converting the data read from `inner`.
+    pub fn reader<R: Read>(&self, inner: R) -> ConverterReader<R> {
+        ConverterReader::new(inner, self.rules, self.final_newline)
+    }
+}
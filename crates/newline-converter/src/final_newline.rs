@@ -0,0 +1,210 @@
+//! Fixing the line breaks at the end of the text, in memory and in streams.
+
+use crate::LineEnding;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::mem;
+
+/// What to do with the line breaks (`\n` and `\r`, and NEL, LS and PS if enabled)
+/// at the end of the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FinalNewline {
+    /// Line ending to add, if the text does not end with one.
+    pub add: Option<LineEnding>,
+    /// Whether to remove blank lines at the end, keeping only the first line ending.
+    pub trim: bool,
+    /// Whether the Unicode line endings (NEL, LS and PS) are line breaks too.
+    pub unicode: bool,
+}
+
+impl FinalNewline {
+    pub fn is_noop(&self) -> bool {
+        self.add.is_none() && !self.trim
+    }
+
+    /// Returns which of the line breaks found at the end of the text to keep, and the line
+    /// ending to add after them. `content` tells whether there is any other text before them.
+    fn end<'a>(&self, newlines: &'a [u8], content: bool) -> (&'a [u8], Option<LineEnding>) {
+        let kept = match (self.trim, content) {
+            (false, _) => newlines,
+            (true, false) => &[],
+            (true, true) => &newlines[..first_len(newlines)],
+        };
+        let added = match self.add {
+            Some(ending) if content && kept.is_empty() => Some(ending),
+            _ => None,
+        };
+        (kept, added)
+    }
+
+    /// Returns the length of `input` to keep, and the line ending to add after it.
+    fn fix(&self, input: &[u8]) -> (usize, Option<LineEnding>) {
+        if self.is_noop() {
+            return (input.len(), None);
+        }
+        let content = self.content_len(input);
+        let (kept, added) = self.end(&input[content..], content > 0);
+        (content + kept.len(), added)
+    }
+
+    pub fn apply_str<'a>(&self, input: Cow<'a, str>) -> Cow<'a, str> {
+        match self.fix(input.as_bytes()) {
+            (len, None) if len == input.len() => input,
+            // only whole line breaks are cut off, so `len` is always at a character boundary
+            (len, None) => match input {
+                Cow::Borrowed(s) => Cow::Borrowed(&s[..len]),
+                Cow::Owned(mut s) => {
+                    s.truncate(len);
+                    Cow::Owned(s)
+                }
+            },
+            (len, Some(ending)) => {
+                let mut output = input.into_owned();
+                output.truncate(len);
+                output.push_str(ending.as_str());
+                Cow::Owned(output)
+            }
+        }
+    }
+
+    pub fn apply_bytes<'a>(&self, input: Cow<'a, [u8]>) -> Cow<'a, [u8]> {
+        match self.fix(&input) {
+            (len, None) if len == input.len() => input,
+            (len, None) => match input {
+                Cow::Borrowed(s) => Cow::Borrowed(&s[..len]),
+                Cow::Owned(mut s) => {
+                    s.truncate(len);
+                    Cow::Owned(s)
+                }
+            },
+            (len, Some(ending)) => {
+                let mut output = input.into_owned();
+                output.truncate(len);
+                output.extend_from_slice(ending.as_bytes());
+                Cow::Owned(output)
+            }
+        }
+    }
+
+    /// Returns the length of `input` without the line breaks at its end.
+    fn content_len(&self, input: &[u8]) -> usize {
+        let mut len = input.len();
+        loop {
+            len -= match &input[..len] {
+                [.., b'\n' | b'\r'] => 1,
+                [.., 0xC2, 0x85] if self.unicode => 2,
+                [.., 0xE2, 0x80, 0xA8 | 0xA9] if self.unicode => 3,
+                _ => return len,
+            };
+        }
+    }
+
+    /// Returns the length of what may be a beginning of NEL, LS or PS at the end of `input`,
+    /// which only the data following it tells.
+    fn partial_len(&self, input: &[u8]) -> usize {
+        match input {
+            _ if !self.unicode => 0,
+            [.., 0xE2, 0x80] => 2,
+            [.., 0xC2 | 0xE2] => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Returns the length of the first line break in `newlines`, which holds line breaks only.
+fn first_len(newlines: &[u8]) -> usize {
+    match newlines {
+        [] => 0,
+        [b'\r', b'\n', ..] | [0xC2, ..] => 2,
+        [0xE2, ..] => 3,
+        _ => 1,
+    }
+}
+
+/// Line breaks at the end of the output so far, held back until some other text follows them,
+/// so they are known to be trailing when finished.
+#[derive(Debug, Default)]
+pub(crate) struct Trailing {
+    pub mode: FinalNewline,
+    newlines: Vec<u8>,
+    /// What may be a beginning of a line break after `newlines`, split by the writes.
+    partial: Vec<u8>,
+    /// Whether any text other than line breaks was written.
+    content: bool,
+}
+
+impl Trailing {
+    pub fn new(mode: FinalNewline) -> Self {
+        Trailing {
+            mode,
+            ..Trailing::default()
+        }
+    }
+
+    /// Returns a writer passing the data through, on its way to `out`.
+    pub fn writer<'a, W: Write>(&'a mut self, out: &'a mut W) -> TrailingWriter<'a, W> {
+        TrailingWriter {
+            trailing: self,
+            out,
+        }
+    }
+
+    /// Writes out the end of the text to `out`.
+    pub fn finish<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            // nothing completed it to a line break
+            out.write_all(&self.newlines)?;
+            out.write_all(&self.partial)?;
+            self.newlines.clear();
+            self.partial.clear();
+            self.content = true;
+        }
+        let (kept, added) = self.mode.end(&self.newlines, self.content);
+        out.write_all(kept)?;
+        if let Some(ending) = added {
+            out.write_all(ending.as_bytes())?;
+        }
+        self.newlines.clear();
+        self.content = false;
+
+        Ok(())
+    }
+}
+
+/// See [`Trailing::writer`].
+pub(crate) struct TrailingWriter<'a, W: Write> {
+    trailing: &'a mut Trailing,
+    out: &'a mut W,
+}
+
+impl<'a, W: Write> Write for TrailingWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let trailing = &mut *self.trailing;
+        let joined;
+        let input = if trailing.partial.is_empty() {
+            buf
+        } else {
+            let mut partial = mem::take(&mut trailing.partial);
+            partial.extend_from_slice(buf);
+            joined = partial;
+            &joined
+        };
+
+        let whole = input.len() - trailing.mode.partial_len(input);
+        let content = trailing.mode.content_len(&input[..whole]);
+        if content > 0 {
+            self.out.write_all(&trailing.newlines)?;
+            trailing.newlines.clear();
+            self.out.write_all(&input[..content])?;
+            trailing.content = true;
+        }
+        trailing.newlines.extend_from_slice(&input[content..whole]);
+        trailing.partial.extend_from_slice(&input[whole..]);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
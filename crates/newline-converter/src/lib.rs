@@ -25,15 +25,89 @@
 //! ```
 //!
 //! The conversion functions are **lazy** - they don't perform any allocations if the input is already in correct format.
+//!
+//! Large inputs can be converted with constant memory usage, using the streaming adapters:
+//!
+//! ```
+//! use std::io::Write;
+//! use newline_converter::Dos2UnixWriter;
+//!
+//! let mut writer = Dos2UnixWriter::new(Vec::new());
+//! writer.write_all(b"foo\r\nbar\r")?;
+//! writer.write_all(b"\n")?;
+//! assert_eq!(writer.finish()?, b"foo\nbar\n");
+//! # Ok::<(), std::io::Error>(())
+//! ```
 
 #![deny(missing_docs)]
 #![deny(clippy::unwrap_used)]
 #![deny(clippy::expect_used)]
 
 use std::borrow::Cow;
-use unicode_segmentation::UnicodeSegmentation;
 
-const UNPACK_MSG: &str = "Grapheme should always be found -- Please file a bug report";
+mod converter;
+pub use converter::Converter;
+mod final_newline;
+use final_newline::FinalNewline;
+mod rules;
+mod scan;
+use scan::Edit;
+mod stream;
+pub use stream::{
+    ConverterReader, ConverterWriter, Dos2MacReader, Dos2MacWriter, Dos2UnixReader, Dos2UnixWriter,
+    FinalNewlineWriter, Mac2DosReader, Mac2DosWriter, Mac2UnixReader, Mac2UnixWriter,
+    NormalizeReader, NormalizeWriter, Unix2DosReader, Unix2DosWriter, Unix2MacReader,
+    Unix2MacWriter,
+};
+
+const UNPACK_MSG: &str = "Conversion should always produce valid UTF-8 -- Please file a bug report";
+
+/// A line ending style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// Line feed (`\n`), used by UNIX-like systems.
+    Lf,
+    /// Carriage return followed by line feed (`\r\n`), used by DOS and Windows.
+    CrLf,
+    /// Carriage return (`\r`), used by classic Mac OS.
+    Cr,
+    /// Unicode next line (`U+0085`).
+    Nel,
+    /// Unicode line separator (`U+2028`).
+    Ls,
+    /// Unicode paragraph separator (`U+2029`).
+    Ps,
+}
+
+impl LineEnding {
+    /// Returns the line ending as a string slice.
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::LineEnding;
+    /// assert_eq!(LineEnding::CrLf.as_str(), "\r\n");
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+            LineEnding::Nel => "\u{85}",
+            LineEnding::Ls => "\u{2028}",
+            LineEnding::Ps => "\u{2029}",
+        }
+    }
+
+    /// Returns the line ending as a byte slice, in UTF-8.
+    pub fn as_bytes(self) -> &'static [u8] {
+        self.as_str().as_bytes()
+    }
+
+    /// Tells whether it is one of the Unicode line endings (NEL, LS or PS).
+    pub(crate) fn is_unicode(self) -> bool {
+        matches!(self, LineEnding::Nel | LineEnding::Ls | LineEnding::Ps)
+    }
+}
 
 /// Converts DOS-style line endings (`\r\n`) to UNIX-style (`\n`).
 ///
@@ -52,42 +126,11 @@ const UNPACK_MSG: &str = "Grapheme should always be found -- Please file a bug r
 ///    "\nfoo\rbar\n"
 ///  );
 /// ```
-pub fn dos2unix<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
-    let mut iter = input.as_ref().chars().peekable();
-
+pub fn dos2unix<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
     let input = input.as_ref();
-    let mut output: Option<String> = None;
-
-    while let Some(current) = iter.next() {
-        if '\r' == current {
-            if let Some('\n') = iter.peek() {
-                // drop it
-                if output.is_none() {
-                    let n = input.chars().filter(|x| *x == '\r').count();
-                    let mut buffer = String::with_capacity(input.len() - n);
-                    let i = input
-                        .grapheme_indices(true)
-                        .find(|(_, x)| *x == "\r\n")
-                        .map_or_else(|| unreachable!("{}", UNPACK_MSG), |(i, _)| i);
-                    let (past, _) = input.split_at(i);
-                    buffer.push_str(past);
-                    output = Some(buffer);
-                }
-                continue;
-            }
-        }
-        if let Some(o) = output.as_mut() {
-            o.push(current);
-        }
-    }
-
-    match output {
-        None => Cow::Borrowed(input),
-        Some(o) => Cow::Owned(o),
-    }
+    scan::edit_str(input, scan::crlf_positions(input.as_bytes()), Edit::Remove)
 }
 
-#[allow(clippy::match_like_matches_macro)] // MSRV 1.38, matches! macro available in 1.42
 /// Converts UNIX-style line endings (`\n`) to DOS-style (`\r\n`).
 ///
 /// The input string may already be in correct format, so this function
@@ -102,49 +145,415 @@ pub fn dos2unix<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
 /// ```
 /// assert_eq!(newline_converter::unix2dos("\nfoo\r\nbar\n"), "\r\nfoo\r\nbar\r\n");
 /// ```
-pub fn unix2dos<T: AsRef<str> + ?Sized>(input: &T) -> Cow<str> {
-    let mut output: Option<String> = None;
-    let mut last_char: Option<char> = None;
+pub fn unix2dos<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    let input = input.as_ref();
+    scan::edit_str(
+        input,
+        scan::lone_lf_positions(input.as_bytes()),
+        Edit::Insert(b'\r'),
+    )
+}
 
+/// Converts DOS-style line endings (`\r\n`) to UNIX-style (`\n`) in a byte slice.
+///
+/// Unlike [`dos2unix`], the input does not have to be valid UTF-8, so this
+/// function can be used on any ASCII-compatible encoding (like Latin-1 or
+/// Windows-1252) without decoding it first.
+///
+/// The input may already be in correct format, so this function
+/// returns `Cow<[u8]>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(
+///     newline_converter::dos2unix_bytes(b"caf\xe9\r\nbar\r\n"),
+///     &b"caf\xe9\nbar\n"[..]
+/// );
+/// ```
+///
+/// Lone `\r` bytes will be preserved:
+/// ```
+/// assert_eq!(
+///     newline_converter::dos2unix_bytes(b"\nfoo\rbar\r\n"),
+///     &b"\nfoo\rbar\n"[..]
+/// );
+/// ```
+pub fn dos2unix_bytes<T: AsRef<[u8]> + ?Sized>(input: &T) -> Cow<'_, [u8]> {
     let input = input.as_ref();
-    for (i, current) in input.chars().enumerate() {
-        if '\n' == current
-            && (i == 0
-                || match last_char {
-                    Some('\r') => false,
-                    _ => true,
-                })
-        {
-            if output.is_none() {
-                let n = input.chars().filter(|x| *x == '\n').count();
-                let mut buffer = String::with_capacity(input.len() + n);
-                let i = input
-                    .grapheme_indices(true)
-                    .find(|(_, x)| *x == "\n")
-                    .map_or_else(|| unreachable!("{}", UNPACK_MSG), |(i, _)| i);
-                let (past, _) = input.split_at(i);
-                buffer.push_str(past);
-                output = Some(buffer);
-            }
-            match output.as_mut() {
-                Some(o) => o.push('\r'),
-                None => unreachable!(),
-            }
-        }
-        last_char = Some(current);
+    scan::edit_bytes(input, scan::crlf_positions(input), Edit::Remove)
+}
+
+/// Converts UNIX-style line endings (`\n`) to DOS-style (`\r\n`) in a byte slice.
+///
+/// Unlike [`unix2dos`], the input does not have to be valid UTF-8, so this
+/// function can be used on any ASCII-compatible encoding (like Latin-1 or
+/// Windows-1252) without decoding it first.
+///
+/// The input may already be in correct format, so this function
+/// returns `Cow<[u8]>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(
+///     newline_converter::unix2dos_bytes(b"caf\xe9\nbar\n"),
+///     &b"caf\xe9\r\nbar\r\n"[..]
+/// );
+/// ```
+///
+/// Already present DOS line breaks are respected:
+/// ```
+/// assert_eq!(
+///     newline_converter::unix2dos_bytes(b"\nfoo\r\nbar\n"),
+///     &b"\r\nfoo\r\nbar\r\n"[..]
+/// );
+/// ```
+pub fn unix2dos_bytes<T: AsRef<[u8]> + ?Sized>(input: &T) -> Cow<'_, [u8]> {
+    let input = input.as_ref();
+    scan::edit_bytes(input, scan::lone_lf_positions(input), Edit::Insert(b'\r'))
+}
+
+/// Converts DOS-style line endings (`\r\n`) to UNIX-style (`\n`) in place.
+///
+/// The conversion only ever shrinks the text, so it is done within the buffer,
+/// without allocating.
+///
+/// # Examples
+/// ```
+/// let mut buffer = String::from("\r\nfoo\r\nbar\rbaz");
+/// newline_converter::dos2unix_in_place(&mut buffer);
+/// assert_eq!(buffer, "\nfoo\nbar\rbaz");
+/// ```
+pub fn dos2unix_in_place(buffer: &mut String) {
+    // SAFETY: only `\r` bytes are removed, which are ASCII, so never a part of a multi-byte
+    // sequence, and the rest of the text is moved as it is, so it stays valid UTF-8.
+    dos2unix_in_place_bytes(unsafe { buffer.as_mut_vec() });
+}
+
+/// Converts DOS-style line endings (`\r\n`) to UNIX-style (`\n`) in place, in a byte buffer.
+///
+/// Like [`dos2unix_bytes`], works with any ASCII-compatible encoding. The conversion
+/// only ever shrinks the buffer, so it is done without allocating.
+///
+/// # Examples
+/// ```
+/// let mut buffer = b"caf\xe9\r\nbar\r\n".to_vec();
+/// newline_converter::dos2unix_in_place_bytes(&mut buffer);
+/// assert_eq!(buffer, b"caf\xe9\nbar\n");
+/// ```
+pub fn dos2unix_in_place_bytes(buffer: &mut Vec<u8>) {
+    let len = scan::remove_crs(buffer);
+    buffer.truncate(len);
+}
+
+/// Converts UNIX-style line endings (`\n`) to DOS-style (`\r\n`), appending the result to `output`.
+///
+/// Reusing the `output` buffer for many inputs saves allocating for each of them.
+///
+/// # Examples
+/// ```
+/// let mut output = String::new();
+/// for record in &["foo\nbar\n", "baz\r\n"] {
+///     output.clear();
+///     newline_converter::unix2dos_into(record, &mut output);
+///     assert!(output.ends_with("\r\n"));
+/// }
+/// assert_eq!(output, "baz\r\n");
+/// ```
+pub fn unix2dos_into<T: AsRef<str> + ?Sized>(input: &T, output: &mut String) {
+    let input = input.as_ref();
+    output.reserve(input.len());
+    let mut start = 0;
+    for i in scan::lone_lf_positions(input.as_bytes()) {
+        output.push_str(&input[start..i]);
+        output.push('\r');
+        start = i;
+    }
+    output.push_str(&input[start..]);
+}
+
+/// Result of a conversion, with counts of what it did to the line endings.
+/// See [`dos2unix_counted`] and [`unix2dos_counted`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Conversion<'a> {
+    /// The converted text, borrowed from the input if nothing had to be changed.
+    pub output: Cow<'a, str>,
+    /// Number of line endings converted.
+    pub converted: usize,
+    /// Number of line endings already in the target style, left untouched.
+    pub untouched: usize,
+    /// Number of lone `\r` characters, which are not converted.
+    pub lone_cr: usize,
+}
+
+/// Like [`dos2unix`], but also tells how many line endings were converted.
+///
+/// # Examples
+/// ```
+/// use newline_converter::dos2unix_counted;
+/// let conversion = dos2unix_counted("foo\r\nbar\nbaz\rqux\r\n");
+/// assert_eq!(conversion.output, "foo\nbar\nbaz\rqux\n");
+/// assert_eq!((conversion.converted, conversion.untouched, conversion.lone_cr), (2, 1, 1));
+/// ```
+pub fn dos2unix_counted<T: AsRef<str> + ?Sized>(input: &T) -> Conversion<'_> {
+    let input = input.as_ref();
+    let mut crlf = 0;
+    let positions = scan::crlf_positions(input.as_bytes()).inspect(|_| crlf += 1);
+    let output = scan::edit_str(input, positions, Edit::Remove);
+    let (lf, cr) = scan::count_lf_cr(input.as_bytes());
+
+    Conversion {
+        output,
+        converted: crlf,
+        untouched: lf - crlf,
+        lone_cr: cr - crlf,
+    }
+}
+
+/// Like [`unix2dos`], but also tells how many line endings were converted.
+///
+/// # Examples
+/// ```
+/// use newline_converter::unix2dos_counted;
+/// let conversion = unix2dos_counted("foo\r\nbar\nbaz\rqux\n");
+/// assert_eq!(conversion.output, "foo\r\nbar\r\nbaz\rqux\r\n");
+/// assert_eq!((conversion.converted, conversion.untouched, conversion.lone_cr), (2, 1, 1));
+/// ```
+pub fn unix2dos_counted<T: AsRef<str> + ?Sized>(input: &T) -> Conversion<'_> {
+    let input = input.as_ref();
+    let mut lone_lf = 0;
+    let positions = scan::lone_lf_positions(input.as_bytes()).inspect(|_| lone_lf += 1);
+    let output = scan::edit_str(input, positions, Edit::Insert(b'\r'));
+    let (lf, cr) = scan::count_lf_cr(input.as_bytes());
+    let crlf = lf - lone_lf;
+
+    Conversion {
+        output,
+        converted: lone_lf,
+        untouched: crlf,
+        lone_cr: cr - crlf,
+    }
+}
+
+/// Converts classic Mac OS line endings (`\r`) to UNIX-style (`\n`).
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(newline_converter::mac2unix("\rfoo\rbar\r"), "\nfoo\nbar\n");
+/// ```
+///
+/// DOS line breaks (`\r\n`) are left untouched:
+/// ```
+/// assert_eq!(newline_converter::mac2unix("\rfoo\r\nbar\n"), "\nfoo\r\nbar\n");
+/// ```
+pub fn mac2unix<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    rules::MAC2UNIX.convert_str(input.as_ref())
+}
+
+/// Converts UNIX-style line endings (`\n`) to classic Mac OS (`\r`).
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(newline_converter::unix2mac("\nfoo\nbar\n"), "\rfoo\rbar\r");
+/// ```
+///
+/// DOS line breaks (`\r\n`) are left untouched:
+/// ```
+/// assert_eq!(newline_converter::unix2mac("\nfoo\r\nbar\r"), "\rfoo\r\nbar\r");
+/// ```
+pub fn unix2mac<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    rules::UNIX2MAC.convert_str(input.as_ref())
+}
+
+/// Converts classic Mac OS line endings (`\r`) to DOS-style (`\r\n`).
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(newline_converter::mac2dos("\rfoo\rbar\r"), "\r\nfoo\r\nbar\r\n");
+/// ```
+///
+/// Already present DOS line breaks are respected, and UNIX ones (`\n`) are left untouched:
+/// ```
+/// assert_eq!(newline_converter::mac2dos("\rfoo\r\nbar\n"), "\r\nfoo\r\nbar\n");
+/// ```
+pub fn mac2dos<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    rules::MAC2DOS.convert_str(input.as_ref())
+}
+
+/// Converts DOS-style line endings (`\r\n`) to classic Mac OS (`\r`).
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// assert_eq!(newline_converter::dos2mac("\r\nfoo\r\nbar\r\n"), "\rfoo\rbar\r");
+/// ```
+///
+/// UNIX line breaks (`\n`) are left untouched:
+/// ```
+/// assert_eq!(newline_converter::dos2mac("\nfoo\r\nbar\r"), "\nfoo\rbar\r");
+/// ```
+pub fn dos2mac<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    rules::DOS2MAC.convert_str(input.as_ref())
+}
+
+/// Converts all line endings to the `target` style.
+///
+/// Unlike the other conversion functions, which only touch one particular kind
+/// of line endings, this one converts any mix of `\n`, `\r\n`, lone `\r`, and
+/// the Unicode [`Nel`](LineEnding::Nel), [`Ls`](LineEnding::Ls) and [`Ps`](LineEnding::Ps).
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// use newline_converter::{normalize, LineEnding};
+/// assert_eq!(normalize("foo\nbar\r\nbaz\r", LineEnding::CrLf), "foo\r\nbar\r\nbaz\r\n");
+/// assert_eq!(normalize("foo\nbar\r\nbaz\u{2028}", LineEnding::Lf), "foo\nbar\nbaz\n");
+/// ```
+pub fn normalize<T: AsRef<str> + ?Sized>(input: &T, target: LineEnding) -> Cow<'_, str> {
+    rules::Rules::normalize(target).convert_str(input.as_ref())
+}
+
+/// Adds a line ending in the `ending` style at the end of `input`, unless it already
+/// ends with one (`\n` or `\r`, or also NEL, LS or PS if `ending` is one of these).
+/// Empty input is left empty.
+///
+/// The input string may already be in correct format, so this function
+/// returns `Cow<str>`, to avoid unnecessary allocation and copying.
+///
+/// # Examples
+/// ```
+/// use newline_converter::{add_final_newline, LineEnding};
+/// assert_eq!(add_final_newline("foo\r\nbar", LineEnding::CrLf), "foo\r\nbar\r\n");
+/// assert_eq!(add_final_newline("foo\n", LineEnding::CrLf), "foo\n");
+/// assert_eq!(add_final_newline("", LineEnding::Lf), "");
+/// assert_eq!(add_final_newline("foo\u{2028}", LineEnding::Ls), "foo\u{2028}");
+/// ```
+pub fn add_final_newline<T: AsRef<str> + ?Sized>(input: &T, ending: LineEnding) -> Cow<'_, str> {
+    let mode = FinalNewline {
+        add: Some(ending),
+        trim: false,
+        unicode: ending.is_unicode(),
+    };
+    mode.apply_str(Cow::Borrowed(input.as_ref()))
+}
+
+/// Removes blank lines at the end of `input`, leaving it ending with a single line ending
+/// (`\n`, `\r\n` or `\r`), if it ended with any. Input made of blank lines only becomes empty.
+///
+/// The result is always a part of the input, so this function never allocates.
+///
+/// # Examples
+/// ```
+/// use newline_converter::trim_trailing_newlines;
+/// assert_eq!(trim_trailing_newlines("foo\r\n\r\n\n"), "foo\r\n");
+/// assert_eq!(trim_trailing_newlines("foo"), "foo");
+/// assert_eq!(trim_trailing_newlines("\n\n"), "");
+/// ```
+pub fn trim_trailing_newlines<T: AsRef<str> + ?Sized>(input: &T) -> Cow<'_, str> {
+    let mode = FinalNewline {
+        add: None,
+        trim: true,
+        unicode: false,
+    };
+    mode.apply_str(Cow::Borrowed(input.as_ref()))
+}
+
+/// Counts of line endings found in the input. See [`analyze`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LineEndingStats {
+    /// Number of UNIX-style line endings (`\n`).
+    pub lf: usize,
+    /// Number of DOS-style line endings (`\r\n`).
+    pub crlf: usize,
+    /// Number of classic Mac OS line endings (lone `\r`).
+    pub cr: usize,
+}
+
+impl LineEndingStats {
+    /// Returns the total number of line endings.
+    pub fn total(&self) -> usize {
+        self.lf + self.crlf + self.cr
+    }
+
+    /// Tells whether more than one kind of line endings is present.
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::analyze;
+    /// assert!(analyze("foo\r\nbar\n").is_mixed());
+    /// assert!(!analyze("foo\r\nbar\r\n").is_mixed());
+    /// ```
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|x| **x > 0)
+            .count()
+            > 1
+    }
 
-        if let Some(o) = output.as_mut() {
-            o.push(current);
+    /// Returns the most common line ending, or `None` if there are no line endings at all.
+    ///
+    /// In case of a tie, [`Lf`](LineEnding::Lf) is preferred over [`CrLf`](LineEnding::CrLf),
+    /// which is preferred over [`Cr`](LineEnding::Cr).
+    ///
+    /// # Examples
+    /// ```
+    /// use newline_converter::{analyze, LineEnding};
+    /// assert_eq!(analyze("foo\r\nbar\r\nbaz\n").dominant(), Some(LineEnding::CrLf));
+    /// assert_eq!(analyze("foo").dominant(), None);
+    /// ```
+    pub fn dominant(&self) -> Option<LineEnding> {
+        if self.total() == 0 {
+            None
+        } else if self.lf >= self.crlf && self.lf >= self.cr {
+            Some(LineEnding::Lf)
+        } else if self.crlf >= self.cr {
+            Some(LineEnding::CrLf)
+        } else {
+            Some(LineEnding::Cr)
         }
     }
+}
 
-    match output {
-        Some(o) => Cow::Owned(o),
-        None => Cow::Borrowed(input),
+/// Counts the line endings in the input, which may be either `&str` or `&[u8]`.
+///
+/// The input does not have to be valid UTF-8, as only `\n`, `\r\n` and lone `\r`
+/// line endings are counted.
+///
+/// # Examples
+/// ```
+/// use newline_converter::{analyze, LineEndingStats};
+/// assert_eq!(
+///     analyze("foo\r\nbar\nbaz\rqux\r\n"),
+///     LineEndingStats { lf: 1, crlf: 2, cr: 1 }
+/// );
+/// assert_eq!(analyze(b"caf\xe9\n").lf, 1);
+/// ```
+pub fn analyze<T: AsRef<[u8]> + ?Sized>(input: &T) -> LineEndingStats {
+    let input = input.as_ref();
+    let (lf, cr) = scan::count_lf_cr(input);
+    let crlf = scan::crlf_positions(input).count();
+
+    LineEndingStats {
+        lf: lf - crlf,
+        crlf,
+        cr: cr - crlf,
     }
 }
 
-/// Extension trait for converting between DOS and UNIX linebreaks.
+/// Extension trait for converting between DOS, UNIX and classic Mac OS linebreaks.
 pub trait AsRefStrExt {
     /// Converts linebreaks to DOS (`\r\n`). See [`unix2dos`] for more info.
     ///
@@ -154,7 +563,7 @@ pub trait AsRefStrExt {
     /// use newline_converter::AsRefStrExt;
     /// assert_eq!("foo\r\nbar", "foo\nbar".to_dos());
     /// ```
-    fn to_dos(&self) -> Cow<str>;
+    fn to_dos(&self) -> Cow<'_, str>;
 
     /// Converts linebreaks to UNIX (`\n`). See [`dos2unix`] for more info.
     ///
@@ -164,26 +573,97 @@ pub trait AsRefStrExt {
     /// use newline_converter::AsRefStrExt;
     /// assert_eq!("foo\nbar", "foo\r\nbar".to_unix());
     /// ```
-    fn to_unix(&self) -> Cow<str>;
+    fn to_unix(&self) -> Cow<'_, str>;
+
+    /// Converts linebreaks to classic Mac OS (`\r`). See [`unix2mac`] for more info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use newline_converter::AsRefStrExt;
+    /// assert_eq!("foo\rbar", "foo\nbar".to_mac());
+    /// ```
+    fn to_mac(&self) -> Cow<'_, str>;
+
+    /// Converts classic Mac OS linebreaks to UNIX (`\n`). See [`mac2unix`] for more info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use newline_converter::AsRefStrExt;
+    /// assert_eq!("foo\nbar", "foo\rbar".mac_to_unix());
+    /// ```
+    fn mac_to_unix(&self) -> Cow<'_, str>;
+
+    /// Converts classic Mac OS linebreaks to DOS (`\r\n`). See [`mac2dos`] for more info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use newline_converter::AsRefStrExt;
+    /// assert_eq!("foo\r\nbar", "foo\rbar".mac_to_dos());
+    /// ```
+    fn mac_to_dos(&self) -> Cow<'_, str>;
+
+    /// Converts DOS linebreaks to classic Mac OS (`\r`). See [`dos2mac`] for more info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use newline_converter::AsRefStrExt;
+    /// assert_eq!("foo\rbar", "foo\r\nbar".dos_to_mac());
+    /// ```
+    fn dos_to_mac(&self) -> Cow<'_, str>;
+
+    /// Converts all linebreaks to the `target` style. See [`normalize`] for more info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use newline_converter::{AsRefStrExt, LineEnding};
+    /// assert_eq!("foo\nbar\nbaz", "foo\r\nbar\rbaz".normalize(LineEnding::Lf));
+    /// ```
+    fn normalize(&self, target: LineEnding) -> Cow<'_, str>;
 }
 
 impl<T> AsRefStrExt for T
 where
     T: AsRef<str>,
 {
-    fn to_dos(&self) -> Cow<str> {
+    fn to_dos(&self) -> Cow<'_, str> {
         unix2dos(self)
     }
 
-    fn to_unix(&self) -> Cow<str> {
+    fn to_unix(&self) -> Cow<'_, str> {
         dos2unix(self)
     }
+
+    fn to_mac(&self) -> Cow<'_, str> {
+        unix2mac(self)
+    }
+
+    fn mac_to_unix(&self) -> Cow<'_, str> {
+        mac2unix(self)
+    }
+
+    fn mac_to_dos(&self) -> Cow<'_, str> {
+        mac2dos(self)
+    }
+
+    fn dos_to_mac(&self) -> Cow<'_, str> {
+        dos2mac(self)
+    }
+
+    fn normalize(&self, target: LineEnding) -> Cow<'_, str> {
+        normalize(self, target)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use quickcheck::{quickcheck, TestResult};
+    use unicode_segmentation::UnicodeSegmentation;
 
     #[test]
     fn middle() {
@@ -283,6 +763,213 @@ mod tests {
         assert_eq!(unix2dos("\n"), "\r\n");
     }
 
+    #[test]
+    fn bytes_dos2unix() {
+        assert_eq!(dos2unix_bytes(b"\r\nfoo\r\nbar\r\n"), &b"\nfoo\nbar\n"[..]);
+        assert_eq!(dos2unix_bytes(b"\nfoo\rbar\r\n"), &b"\nfoo\rbar\n"[..]);
+    }
+
+    #[test]
+    fn bytes_unix2dos() {
+        assert_eq!(unix2dos_bytes(b"\nfoo\nbar\n"), &b"\r\nfoo\r\nbar\r\n"[..]);
+        assert_eq!(unix2dos_bytes(b"\rfoo\r\nbar\n"), &b"\rfoo\r\nbar\r\n"[..]);
+    }
+
+    #[test]
+    fn bytes_invalid_utf8() {
+        // "Zażółć" in ISO-8859-2
+        assert_eq!(
+            dos2unix_bytes(b"Za\xbf\xf3\xb3\xe6\r\n"),
+            &b"Za\xbf\xf3\xb3\xe6\n"[..]
+        );
+        assert_eq!(
+            unix2dos_bytes(b"Za\xbf\xf3\xb3\xe6\n"),
+            &b"Za\xbf\xf3\xb3\xe6\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn not_mutated_bytes() {
+        let converted = dos2unix_bytes(b"\nfoo\nbar\n");
+        assert_eq!(converted, Cow::Borrowed(&b"\nfoo\nbar\n"[..]) as Cow<[u8]>);
+        let converted = unix2dos_bytes(b"\r\nfoo\r\nbar\r\n");
+        assert_eq!(
+            converted,
+            Cow::Borrowed(&b"\r\nfoo\r\nbar\r\n"[..]) as Cow<[u8]>
+        );
+    }
+
+    #[test]
+    fn mac() {
+        assert_eq!(mac2unix("\rfoo\rbar\r"), "\nfoo\nbar\n");
+        assert_eq!(unix2mac("\nfoo\nbar\n"), "\rfoo\rbar\r");
+        assert_eq!(mac2dos("\rfoo\rbar\r"), "\r\nfoo\r\nbar\r\n");
+        assert_eq!(dos2mac("\r\nfoo\r\nbar\r\n"), "\rfoo\rbar\r");
+    }
+
+    #[test]
+    fn mac_advanced() {
+        assert_eq!(mac2unix("\rfoo\r\nbar\n\r"), "\nfoo\r\nbar\n\n");
+        assert_eq!(unix2mac("\rfoo\r\nbar\n\r"), "\rfoo\r\nbar\r\r");
+        assert_eq!(mac2dos("\rfoo\r\nbar\n\r"), "\r\nfoo\r\nbar\n\r\n");
+        assert_eq!(dos2mac("\rfoo\r\nbar\n\r"), "\rfoo\rbar\n\r");
+    }
+
+    #[test]
+    fn non_ascii_characters_mac() {
+        assert_eq!(mac2unix("Zażółć\rgęślą\rjaźń\r"), "Zażółć\ngęślą\njaźń\n");
+        assert_eq!(unix2mac("Zażółć\ngęślą\njaźń\n"), "Zażółć\rgęślą\rjaźń\r");
+    }
+
+    #[test]
+    fn not_mutated_mac() {
+        let input = "\nfoo\r\nbar\n";
+        assert_eq!(mac2unix(input), Cow::Borrowed(input) as Cow<str>);
+        assert_eq!(mac2dos(input), Cow::Borrowed(input) as Cow<str>);
+        let input = "\rfoo\r\nbar\r";
+        assert_eq!(unix2mac(input), Cow::Borrowed(input) as Cow<str>);
+        let input = "\rfoo\nbar\r";
+        assert_eq!(dos2mac(input), Cow::Borrowed(input) as Cow<str>);
+    }
+
+    #[test]
+    fn normalize_mixed() {
+        let input = "a\nb\r\nc\rd\u{85}e\u{2028}f\u{2029}";
+        assert_eq!(normalize(input, LineEnding::Lf), "a\nb\nc\nd\ne\nf\n");
+        assert_eq!(
+            normalize(input, LineEnding::CrLf),
+            "a\r\nb\r\nc\r\nd\r\ne\r\nf\r\n"
+        );
+        assert_eq!(normalize(input, LineEnding::Cr), "a\rb\rc\rd\re\rf\r");
+        assert_eq!(
+            normalize(input, LineEnding::Nel),
+            "a\u{85}b\u{85}c\u{85}d\u{85}e\u{85}f\u{85}"
+        );
+    }
+
+    #[test]
+    fn normalize_non_ascii_characters() {
+        // U+2019 and U+00A0 share the first UTF-8 byte with LS/PS and NEL
+        assert_eq!(
+            normalize("it\u{2019}s\u{a0}ok\r\n", LineEnding::Lf),
+            "it\u{2019}s\u{a0}ok\n"
+        );
+    }
+
+    #[test]
+    fn not_mutated_normalize() {
+        let input = "\r\nfoo\r\nbar\r\n";
+        assert_eq!(
+            normalize(input, LineEnding::CrLf),
+            Cow::Borrowed(input) as Cow<str>
+        );
+    }
+
+    #[test]
+    fn analyze_counts() {
+        let stats = analyze("\rfoo\r\nbar\n\r\n\r");
+        assert_eq!(
+            stats,
+            LineEndingStats {
+                lf: 1,
+                crlf: 2,
+                cr: 2
+            }
+        );
+        assert_eq!(stats.total(), 5);
+        assert!(stats.is_mixed());
+        assert_eq!(stats.dominant(), Some(LineEnding::CrLf));
+    }
+
+    #[test]
+    fn analyze_empty() {
+        let stats = analyze("");
+        assert_eq!(stats, LineEndingStats::default());
+        assert!(!stats.is_mixed());
+        assert_eq!(stats.dominant(), None);
+    }
+
+    #[test]
+    fn converter_strips_trailing_whitespace() {
+        let dos2unix = Converter::dos2unix().strip_trailing_whitespace(true);
+        assert_eq!(
+            dos2unix.convert("foo \r\n\t\r\nbar\t \rbaz "),
+            "foo\n\nbar\rbaz"
+        );
+        assert_eq!(dos2unix.convert("a b\n c\n"), "a b\n c\n");
+        let normalize = Converter::normalize(LineEnding::Lf).strip_trailing_whitespace(true);
+        assert_eq!(normalize.convert("foo \u{2028}bar\t\u{85}"), "foo\nbar\n");
+        // other whitespace is kept
+        assert_eq!(normalize.convert("foo\u{a0}\n"), "foo\u{a0}\n");
+    }
+
+    #[test]
+    fn converter_fixes_end_with_unicode_line_endings() {
+        let ls = Converter::normalize(LineEnding::Ls).add_final_newline(true);
+        assert_eq!(ls.convert("foo\nbar\n"), "foo\u{2028}bar\u{2028}");
+        assert_eq!(ls.convert("foo\nbar"), "foo\u{2028}bar\u{2028}");
+        // an em dash begins like LS and PS
+        assert_eq!(ls.convert("foo\u{2014}"), "foo\u{2014}\u{2028}");
+        let nel = Converter::normalize(LineEnding::Nel).trim_trailing_newlines(true);
+        assert_eq!(nel.convert("foo\n\n\n"), "foo\u{85}");
+        assert_eq!(nel.convert("foo\u{2029}\r\n\u{85}"), "foo\u{85}");
+        assert_eq!(nel.convert("\u{2028}\u{85}"), "");
+        // other conversions don't take them for line endings
+        let dos2unix = Converter::dos2unix()
+            .add_final_newline(true)
+            .trim_trailing_newlines(true);
+        assert_eq!(dos2unix.convert("foo\u{2028}"), "foo\u{2028}\n");
+    }
+
+    #[test]
+    fn add_final_newline_unicode() {
+        assert_eq!(
+            add_final_newline("foo\u{2028}", LineEnding::Ls),
+            "foo\u{2028}"
+        );
+        assert_eq!(add_final_newline("foo\u{85}", LineEnding::Ps), "foo\u{85}");
+        assert_eq!(
+            add_final_newline("foo\u{2028}", LineEnding::Lf),
+            "foo\u{2028}\n"
+        );
+    }
+
+    #[test]
+    fn converter_not_mutated() {
+        let converter = Converter::unix2dos().strip_trailing_whitespace(true);
+        let converted = converter.convert("foo\r\n bar\r\n");
+        assert_eq!(converted, Cow::Borrowed("foo\r\n bar\r\n") as Cow<str>);
+        assert!(is_borrowed(converted));
+    }
+
+    fn is_borrowed(cow: Cow<'_, str>) -> bool {
+        match cow {
+            Cow::Borrowed(_) => true,
+            Cow::Owned(_) => false,
+        }
+    }
+
+    /// Removes spaces and tabs before each `\r` and `\n`, and at the end.
+    fn strip_trailing_whitespace(data: &str) -> String {
+        let mut output = String::new();
+        let mut whitespace = String::new();
+        for c in data.chars() {
+            match c {
+                ' ' | '\t' => whitespace.push(c),
+                '\r' | '\n' => {
+                    whitespace.clear();
+                    output.push(c);
+                }
+                _ => {
+                    output.push_str(&whitespace);
+                    whitespace.clear();
+                    output.push(c);
+                }
+            }
+        }
+        output
+    }
+
     quickcheck! {
         fn dos_unix_dos(data: String) -> TestResult {
             if data.contains("\r\n") {
@@ -315,5 +1002,187 @@ mod tests {
         fn to_dos_equals_unix2dos(data: String) -> bool {
             unix2dos(&data) == data.to_dos()
         }
+
+        fn dos2unix_bytes_equals_dos2unix(data: String) -> bool {
+            dos2unix_bytes(&data) == dos2unix(&data).as_bytes()
+        }
+
+        fn unix2dos_bytes_equals_unix2dos(data: String) -> bool {
+            unix2dos_bytes(&data) == unix2dos(&data).as_bytes()
+        }
+
+        fn dos2unix_in_place_equals_dos2unix(data: String) -> bool {
+            let mut buffer = data.clone();
+            dos2unix_in_place(&mut buffer);
+            buffer == dos2unix(&data)
+        }
+
+        fn dos2unix_in_place_bytes_equals_dos2unix_bytes(data: Vec<u8>) -> bool {
+            let mut buffer = data.clone();
+            dos2unix_in_place_bytes(&mut buffer);
+            buffer == dos2unix_bytes(&data).as_ref()
+        }
+
+        fn counted_agree_with_analyze(data: String) -> bool {
+            let stats = analyze(&data);
+            let to_unix = dos2unix_counted(&data);
+            let to_dos = unix2dos_counted(&data);
+
+            to_unix.output == dos2unix(&data)
+                && to_dos.output == unix2dos(&data)
+                && (to_unix.converted, to_unix.untouched, to_unix.lone_cr)
+                    == (stats.crlf, stats.lf, stats.cr)
+                && (to_dos.converted, to_dos.untouched, to_dos.lone_cr)
+                    == (stats.lf, stats.crlf, stats.cr)
+        }
+
+        fn unix2dos_into_appends_unix2dos(prefix: String, data: String) -> bool {
+            let mut buffer = prefix.clone();
+            unix2dos_into(&data, &mut buffer);
+            buffer == prefix + &unix2dos(&data)
+        }
+
+        fn dos2unix_equals_rules(data: Vec<u8>) -> bool {
+            dos2unix_bytes(&data) == rules::DOS2UNIX.convert_bytes(&data)
+        }
+
+        fn unix2dos_equals_rules(data: Vec<u8>) -> bool {
+            unix2dos_bytes(&data) == rules::UNIX2DOS.convert_bytes(&data)
+        }
+
+        fn mac_unix_mac(data: String) -> TestResult {
+            if data.contains('\r') {
+                return TestResult::discard();
+            }
+
+            TestResult::from_bool(mac2unix(&unix2mac(&data)) == data)
+        }
+
+        fn mac_contains_no_lone_lf(data: String) -> bool {
+            let mac = unix2mac(&data);
+            let crlf = mac.matches("\r\n").count();
+            let lf = mac.chars().filter(|x| *x == '\n').count();
+
+            lf == crlf
+        }
+
+        fn mac_dos_mac(data: String) -> bool {
+            dos2mac(&mac2dos(&data)) == dos2mac(&data)
+        }
+
+        fn normalize_equals_line_replace(data: String) -> bool {
+            let expected = data
+                .replace("\r\n", "\n")
+                .replace('\r', "\n")
+                .replace(&['\u{85}', '\u{2028}', '\u{2029}'][..], "\n")
+                .replace('\n', "\r\n");
+
+            normalize(&data, LineEnding::CrLf) == expected
+        }
+
+        fn add_final_newline_adds_only_if_missing(data: String) -> bool {
+            let added = add_final_newline(&data, LineEnding::CrLf);
+            if data.is_empty() || data.ends_with(&['\r', '\n'][..]) {
+                added == data
+            } else {
+                added == format!("{}\r\n", data)
+            }
+        }
+
+        fn trim_trailing_newlines_keeps_content(data: String) -> bool {
+            let trimmed = trim_trailing_newlines(&data);
+            let content = data.trim_end_matches(&['\r', '\n'][..]);
+            let rest = &trimmed[content.len()..];
+            trimmed.starts_with(content)
+                && ["", "\n", "\r", "\r\n"].contains(&rest)
+                && (content.is_empty() == trimmed.is_empty())
+        }
+
+        fn normalize_is_idempotent(data: String) -> bool {
+            let normalized = normalize(&data, LineEnding::CrLf);
+            normalize(&normalized, LineEnding::CrLf) == Cow::Borrowed(normalized.as_ref())
+        }
+
+        fn analyze_counts_bytes(data: Vec<u8>) -> bool {
+            let stats = analyze(&data);
+            let lf = data.iter().filter(|x| **x == b'\n').count();
+            let cr = data.iter().filter(|x| **x == b'\r').count();
+            let crlf = data.windows(2).filter(|x| *x == b"\r\n").count();
+
+            stats.crlf == crlf && stats.lf + stats.crlf == lf && stats.cr + stats.crlf == cr
+        }
+
+        fn analyze_after_normalize_is_not_mixed(data: String) -> bool {
+            let stats = analyze(normalize(&data, LineEnding::CrLf).as_ref());
+
+            !stats.is_mixed() && stats.lf == 0 && stats.cr == 0
+        }
+
+        fn bytes_unix_dos_unix(data: Vec<u8>) -> bool {
+            dos2unix_bytes(&unix2dos_bytes(&data)) == dos2unix_bytes(&data)
+        }
+
+        fn converter_equals_functions(data: String) -> bool {
+            Converter::dos2unix().convert(&data) == dos2unix(&data)
+                && Converter::unix2dos().convert(&data) == unix2dos(&data)
+                && Converter::mac2unix().convert(&data) == mac2unix(&data)
+                && Converter::dos2mac().convert(&data) == dos2mac(&data)
+                && Converter::normalize(LineEnding::Cr).convert(&data)
+                    == normalize(&data, LineEnding::Cr)
+        }
+
+        fn converter_strips_after_converting(data: String) -> bool {
+            let dos2unix_stripping = Converter::dos2unix().strip_trailing_whitespace(true);
+            let unix2dos_stripping = Converter::unix2dos().strip_trailing_whitespace(true);
+            let mac2dos_stripping = Converter::mac2dos().strip_trailing_whitespace(true);
+
+            dos2unix_stripping.convert(&data) == strip_trailing_whitespace(&dos2unix(&data))
+                && unix2dos_stripping.convert(&data) == strip_trailing_whitespace(&unix2dos(&data))
+                && mac2dos_stripping.convert(&data) == strip_trailing_whitespace(&mac2dos(&data))
+        }
+
+        fn converter_borrows_when_unchanged(data: String) -> bool {
+            let converter = Converter::normalize(LineEnding::Lf).strip_trailing_whitespace(true);
+            let converted = converter.convert(&data);
+            let unchanged = converted == data;
+            is_borrowed(converted) == unchanged
+        }
+
+        fn converter_fixes_end_of_normalized_text(
+            data: String,
+            target: u8,
+            add: bool,
+            trim: bool
+        ) -> bool {
+            let endings = [
+                LineEnding::Lf,
+                LineEnding::CrLf,
+                LineEnding::Cr,
+                LineEnding::Nel,
+                LineEnding::Ls,
+                LineEnding::Ps,
+            ];
+            let target = endings[target as usize % endings.len()];
+            let converter = Converter::normalize(target)
+                .add_final_newline(add)
+                .trim_trailing_newlines(trim);
+
+            let normalized = normalize(&data, target);
+            let content = normalized.trim_end_matches(target.as_str());
+            let trailing = (normalized.len() - content.len()) / target.as_str().len();
+            let kept = match (trim, content.is_empty()) {
+                (false, _) => trailing,
+                (true, true) => 0,
+                (true, false) => trailing.min(1),
+            };
+            let added = add && !content.is_empty() && kept == 0;
+            let expected = format!("{}{}", content, target.as_str().repeat(kept + added as usize));
+            converter.convert(&data) == expected
+        }
+
+        fn converter_bytes_equals_str(data: String) -> bool {
+            let converter = Converter::unix2mac().strip_trailing_whitespace(true);
+            converter.convert_bytes(&data).as_ref() == converter.convert(&data).as_bytes()
+        }
     }
 }
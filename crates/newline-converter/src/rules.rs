@@ -0,0 +1,204 @@
+//! Generic line ending conversion, driven by a set of replacement rules.
+
+use crate::{LineEnding, UNPACK_MSG};
+use std::borrow::Cow;
+
+/// Tells what each kind of line ending should be replaced with.
+///
+/// A line ending replaced with itself is left untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rules {
+    /// Replacement for `\n`, not preceded by `\r`.
+    pub lf: &'static [u8],
+    /// Replacement for `\r\n`.
+    pub crlf: &'static [u8],
+    /// Replacement for `\r`, not followed by `\n`.
+    pub cr: &'static [u8],
+    /// Replacement for Unicode line endings (NEL, LS and PS),
+    /// or `None` if these should not be treated as line endings.
+    pub unicode: Option<&'static [u8]>,
+    /// Whether to remove spaces and tabs at the end of each line.
+    pub strip_trailing_whitespace: bool,
+}
+
+/// Result of looking for a line ending at some position of the input.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Scan {
+    /// There is no line ending there.
+    Other,
+    /// There is a line ending of given length, which should be replaced with given bytes.
+    LineEnding(usize, &'static [u8]),
+    /// The input ends with what may be a beginning of a line ending.
+    Incomplete,
+}
+
+pub(crate) const DOS2UNIX: Rules = Rules {
+    lf: b"\n",
+    crlf: b"\n",
+    cr: b"\r",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+pub(crate) const UNIX2DOS: Rules = Rules {
+    lf: b"\r\n",
+    crlf: b"\r\n",
+    cr: b"\r",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+pub(crate) const MAC2UNIX: Rules = Rules {
+    lf: b"\n",
+    crlf: b"\r\n",
+    cr: b"\n",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+pub(crate) const UNIX2MAC: Rules = Rules {
+    lf: b"\r",
+    crlf: b"\r\n",
+    cr: b"\r",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+pub(crate) const MAC2DOS: Rules = Rules {
+    lf: b"\n",
+    crlf: b"\r\n",
+    cr: b"\r\n",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+pub(crate) const DOS2MAC: Rules = Rules {
+    lf: b"\n",
+    crlf: b"\r",
+    cr: b"\r",
+    unicode: None,
+    strip_trailing_whitespace: false,
+};
+
+impl Rules {
+    /// Rules replacing every kind of line ending with `target`.
+    pub fn normalize(target: LineEnding) -> Rules {
+        let target = target.as_bytes();
+        Rules {
+            lf: target,
+            crlf: target,
+            cr: target,
+            unicode: Some(target),
+            strip_trailing_whitespace: false,
+        }
+    }
+
+    /// Checks if there is a line ending starting at `i`.
+    #[inline]
+    pub fn at(&self, input: &[u8], i: usize) -> Scan {
+        match input[i] {
+            b'\n' => Scan::LineEnding(1, self.lf),
+            b'\r' => match input.get(i + 1) {
+                Some(b'\n') => Scan::LineEnding(2, self.crlf),
+                Some(_) => Scan::LineEnding(1, self.cr),
+                None => Scan::Incomplete,
+            },
+            0xC2 => match self.unicode {
+                // NEL (U+0085)
+                Some(r) => match input.get(i + 1) {
+                    Some(0x85) => Scan::LineEnding(2, r),
+                    Some(_) => Scan::Other,
+                    None => Scan::Incomplete,
+                },
+                None => Scan::Other,
+            },
+            0xE2 => match self.unicode {
+                // LS (U+2028) and PS (U+2029)
+                Some(r) => match (input.get(i + 1), input.get(i + 2)) {
+                    (Some(0x80), Some(0xA8)) | (Some(0x80), Some(0xA9)) => Scan::LineEnding(3, r),
+                    (Some(0x80), None) | (None, _) => Scan::Incomplete,
+                    _ => Scan::Other,
+                },
+                None => Scan::Other,
+            },
+            _ => Scan::Other,
+        }
+    }
+
+    /// Like [`at`](Rules::at), but knowing that no more input follows.
+    #[inline]
+    pub fn at_end(&self, input: &[u8], i: usize) -> Option<(usize, &'static [u8])> {
+        match self.at(input, i) {
+            Scan::LineEnding(len, replacement) => Some((len, replacement)),
+            Scan::Incomplete if input[i] == b'\r' => Some((1, self.cr)),
+            _ => None,
+        }
+    }
+
+    /// Returns where the whitespace to strip at the end of `line` begins,
+    /// or its end if there is none to strip.
+    #[inline]
+    pub fn trailing_whitespace(&self, line: &[u8]) -> usize {
+        if !self.strip_trailing_whitespace {
+            return line.len();
+        }
+        line.iter()
+            .rposition(|&b| b != b' ' && b != b'\t')
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Converts `input` according to the rules. Allocates only if anything has to be replaced.
+    pub fn convert_bytes<'a>(&self, input: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut output: Option<Vec<u8>> = None;
+        let mut start = 0;
+        let mut line_start = 0;
+        let mut i = 0;
+
+        while i < input.len() {
+            let (len, replacement) = match self.at_end(input, i) {
+                Some(x) => x,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let line_end = line_start + self.trailing_whitespace(&input[line_start..i]);
+            if line_end < i || replacement != &input[i..i + len] {
+                let o = output.get_or_insert_with(|| Vec::with_capacity(input.len()));
+                o.extend_from_slice(&input[start..line_end]);
+                o.extend_from_slice(replacement);
+                start = i + len;
+            }
+            i += len;
+            line_start = i;
+        }
+
+        let end = line_start + self.trailing_whitespace(&input[line_start..]);
+        if end < input.len() {
+            let o = output.get_or_insert_with(|| Vec::with_capacity(input.len()));
+            o.extend_from_slice(&input[start..end]);
+            start = input.len();
+        }
+
+        match output {
+            None => Cow::Borrowed(input),
+            Some(mut o) => {
+                o.extend_from_slice(&input[start..]);
+                Cow::Owned(o)
+            }
+        }
+    }
+
+    /// Converts `input` according to the rules. Allocates only if anything has to be replaced.
+    pub fn convert_str<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        // The input is only cut before ASCII bytes (`\r`, `\n`, spaces and tabs) and before
+        // NEL, LS and PS, which are matched as whole characters, and replaced with whole
+        // characters, so the UTF-8 stays valid.
+        match self.convert_bytes(input.as_bytes()) {
+            Cow::Borrowed(_) => Cow::Borrowed(input),
+            Cow::Owned(o) => {
+                Cow::Owned(String::from_utf8(o).unwrap_or_else(|_| unreachable!("{}", UNPACK_MSG)))
+            }
+        }
+    }
+}
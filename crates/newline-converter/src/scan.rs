@@ -0,0 +1,128 @@
+//! Byte-level kernels for the most common conversions.
+//!
+//! `\r` and `\n` are ASCII, so they never appear inside multi-byte UTF-8 sequences, and the input
+//! can be searched for them with `memchr`, copying everything between the matches in bulk.
+
+use memchr::{memchr, memchr_iter};
+use std::borrow::Cow;
+
+/// Positions of `\r` followed by `\n`, which DOS to UNIX conversion removes.
+pub(crate) fn crlf_positions(input: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    memchr_iter(b'\r', input).filter(move |&i| input.get(i + 1) == Some(&b'\n'))
+}
+
+/// Positions of `\n` not preceded by `\r`, which UNIX to DOS conversion prefixes with `\r`.
+pub(crate) fn lone_lf_positions(input: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    memchr_iter(b'\n', input).filter(move |&i| i == 0 || input[i - 1] != b'\r')
+}
+
+/// Counts all the `\n` and `\r` bytes in `input`.
+pub(crate) fn count_lf_cr(input: &[u8]) -> (usize, usize) {
+    (
+        memchr_iter(b'\n', input).count(),
+        memchr_iter(b'\r', input).count(),
+    )
+}
+
+/// What to do at each of the positions found.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Edit {
+    /// Remove the byte.
+    Remove,
+    /// Insert the byte before it.
+    Insert(u8),
+}
+
+impl Edit {
+    /// Size of the output, which holds at most `input_len` bytes and its edits, to allocate up front.
+    fn capacity(self, input_len: usize) -> usize {
+        match self {
+            Edit::Remove => input_len,
+            // a line is rarely shorter than this, and the buffer grows if it is
+            Edit::Insert(_) => input_len + input_len / 16,
+        }
+    }
+}
+
+/// Applies `edit` at each of `positions` in `input`. Allocates only if there are any.
+pub(crate) fn edit_bytes<I>(input: &[u8], positions: I, edit: Edit) -> Cow<'_, [u8]>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut output: Option<Vec<u8>> = None;
+    let mut start = 0;
+
+    for i in positions {
+        let o = output.get_or_insert_with(|| Vec::with_capacity(edit.capacity(input.len())));
+        o.extend_from_slice(&input[start..i]);
+        start = match edit {
+            Edit::Remove => i + 1,
+            Edit::Insert(b) => {
+                o.push(b);
+                i
+            }
+        };
+    }
+
+    match output {
+        None => Cow::Borrowed(input),
+        Some(mut o) => {
+            o.extend_from_slice(&input[start..]);
+            Cow::Owned(o)
+        }
+    }
+}
+
+/// Like [`edit_bytes`], for string slices. Edits must be at, and insert, ASCII bytes only.
+pub(crate) fn edit_str<I>(input: &str, positions: I, edit: Edit) -> Cow<'_, str>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut output: Option<String> = None;
+    let mut start = 0;
+
+    for i in positions {
+        let o = output.get_or_insert_with(|| String::with_capacity(edit.capacity(input.len())));
+        o.push_str(&input[start..i]);
+        start = match edit {
+            Edit::Remove => i + 1,
+            Edit::Insert(b) => {
+                o.push(char::from(b));
+                i
+            }
+        };
+    }
+
+    match output {
+        None => Cow::Borrowed(input),
+        Some(mut o) => {
+            o.push_str(&input[start..]);
+            Cow::Owned(o)
+        }
+    }
+}
+
+/// Removes `\r` of each `\r\n` in `buffer`, moving the rest of it back.
+/// Returns the length of the converted part, at the beginning of `buffer`.
+pub(crate) fn remove_crs(buffer: &mut [u8]) -> usize {
+    // end of the converted part, and beginning of the part not moved yet
+    let (mut end, mut start) = (0, 0);
+    let mut from = 0;
+
+    while let Some(i) = memchr(b'\r', &buffer[from..]).map(|i| from + i) {
+        if buffer.get(i + 1) == Some(&b'\n') {
+            // nothing has to move until the first `\r` is removed
+            if start != end {
+                buffer.copy_within(start..i, end);
+            }
+            end += i - start;
+            start = i + 1;
+        }
+        from = i + 1;
+    }
+    if start != end {
+        buffer.copy_within(start.., end);
+    }
+
+    end + buffer.len() - start
+}
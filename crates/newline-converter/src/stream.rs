@@ -0,0 +1,1326 @@
+//! Streaming conversion over [`Read`] and [`Write`].
+//!
+//! These adapters convert the data as it passes through them, keeping only
+//! a constant amount of state between calls, so arbitrarily large inputs
+//! can be processed without loading them into memory. A line ending split
+//! between two chunks (like a `\r\n` pair) is handled correctly.
+
+use crate::final_newline::{FinalNewline, Trailing};
+use crate::rules::{Rules, Scan, DOS2MAC, DOS2UNIX, MAC2DOS, MAC2UNIX, UNIX2DOS, UNIX2MAC};
+use crate::{scan, LineEnding};
+use std::io::{self, Read, Write};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// What is held back between chunks.
+#[derive(Debug, Default)]
+struct Pending {
+    /// What may be a beginning of a line ending, at the end of the data so far.
+    carry: Vec<u8>,
+    /// Spaces and tabs at the end of the data so far (before `carry`), dropped if a line
+    /// ending follows them. Only held back when stripping trailing whitespace.
+    whitespace: Vec<u8>,
+    /// Number of line endings replaced so far.
+    converted: usize,
+}
+
+/// Writes `buf` converted according to `rules` into `out`.
+///
+/// What may be a beginning of a line ending at the very end of `buf` (like `\r`,
+/// which may be followed by `\n` in the next chunk) is not written, but held back
+/// in `pending` instead, and so is the whitespace which may turn out to be trailing.
+fn convert_chunk<W: Write>(
+    buf: &[u8],
+    rules: &Rules,
+    pending: &mut Pending,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut buf = buf;
+    if buf.is_empty() {
+        return Ok(());
+    }
+    match *rules {
+        DOS2UNIX => return remove_crs_chunk(buf, pending, out),
+        UNIX2DOS => return insert_crs_chunk(buf, pending, out),
+        _ => {}
+    }
+
+    if !pending.carry.is_empty() {
+        // Line endings are at most 3 bytes long, so this is enough to tell
+        // what the carried bytes are.
+        let mut probe = [0; 4];
+        let n = pending.carry.len();
+        let k = buf.len().min(probe.len() - n);
+        probe[..n].copy_from_slice(&pending.carry);
+        probe[n..n + k].copy_from_slice(&buf[..k]);
+        match rules.at(&probe[..n + k], 0) {
+            Scan::Incomplete => {
+                pending.carry.extend_from_slice(buf);
+                return Ok(());
+            }
+            Scan::LineEnding(len, replacement) => {
+                if replacement != &probe[..len] {
+                    pending.converted += 1;
+                }
+                pending.whitespace.clear();
+                out.write_all(replacement)?;
+                buf = &buf[len - n..];
+            }
+            Scan::Other => {
+                out.write_all(&pending.whitespace)?;
+                pending.whitespace.clear();
+                out.write_all(&pending.carry)?;
+            }
+        }
+        pending.carry.clear();
+    }
+
+    if !pending.whitespace.is_empty() {
+        // the whitespace is trailing only if a line ending follows it
+        match buf.iter().position(|&b| b != b' ' && b != b'\t') {
+            None => {
+                pending.whitespace.extend_from_slice(buf);
+                return Ok(());
+            }
+            Some(i) => {
+                if let Scan::Other = rules.at(buf, i) {
+                    out.write_all(&pending.whitespace)?;
+                    pending.whitespace.clear();
+                }
+            }
+        }
+    }
+
+    let mut start = 0;
+    let mut line_start = 0;
+    let mut i = 0;
+    while i < buf.len() {
+        match rules.at(buf, i) {
+            Scan::Other => i += 1,
+            Scan::Incomplete => {
+                let line_end = line_start + rules.trailing_whitespace(&buf[line_start..i]);
+                out.write_all(&buf[start..line_end])?;
+                pending.whitespace.extend_from_slice(&buf[line_end..i]);
+                pending.carry.extend_from_slice(&buf[i..]);
+                return Ok(());
+            }
+            Scan::LineEnding(len, replacement) => {
+                let line_end = line_start + rules.trailing_whitespace(&buf[line_start..i]);
+                let replaced = replacement != &buf[i..i + len];
+                if replaced {
+                    pending.converted += 1;
+                }
+                if replaced || line_end < i {
+                    out.write_all(&buf[start..line_end])?;
+                    out.write_all(replacement)?;
+                    start = i + len;
+                }
+                pending.whitespace.clear();
+                i += len;
+                line_start = i;
+            }
+        }
+    }
+
+    let line_end = line_start + rules.trailing_whitespace(&buf[line_start..]);
+    out.write_all(&buf[start..line_end])?;
+    pending.whitespace.extend_from_slice(&buf[line_end..]);
+
+    Ok(())
+}
+
+/// [`convert_chunk`] for [`DOS2UNIX`], finding the `\r\n` pairs with `memchr`.
+/// Only a `\r` at the end of `buf` is ever held back.
+fn remove_crs_chunk<W: Write>(buf: &[u8], pending: &mut Pending, out: &mut W) -> io::Result<()> {
+    let mut buf = buf;
+    if !pending.carry.is_empty() {
+        if buf[0] == b'\n' {
+            pending.converted += 1;
+        } else {
+            out.write_all(&pending.carry)?;
+        }
+        pending.carry.clear();
+    }
+    if let Some((&b'\r', rest)) = buf.split_last() {
+        pending.carry.push(b'\r');
+        buf = rest;
+    }
+
+    let mut start = 0;
+    for i in scan::crlf_positions(buf) {
+        out.write_all(&buf[start..i])?;
+        pending.converted += 1;
+        start = i + 1;
+    }
+    out.write_all(&buf[start..])
+}
+
+/// [`convert_chunk`] for [`UNIX2DOS`], finding the lone `\n` with `memchr`.
+/// Only a `\r` at the end of `buf` is ever held back.
+fn insert_crs_chunk<W: Write>(buf: &[u8], pending: &mut Pending, out: &mut W) -> io::Result<()> {
+    let mut buf = buf;
+    if !pending.carry.is_empty() {
+        out.write_all(&pending.carry)?;
+        pending.carry.clear();
+        // `\n` after the `\r` is not lone
+        if buf[0] == b'\n' {
+            out.write_all(b"\n")?;
+            buf = &buf[1..];
+        }
+    }
+    if let Some((&b'\r', rest)) = buf.split_last() {
+        pending.carry.push(b'\r');
+        buf = rest;
+    }
+
+    let mut start = 0;
+    for i in scan::lone_lf_positions(buf) {
+        out.write_all(&buf[start..i])?;
+        out.write_all(b"\r")?;
+        pending.converted += 1;
+        start = i;
+    }
+    out.write_all(&buf[start..])
+}
+
+/// Writes out what is held back at the end of the input.
+fn finish_chunks<W: Write>(rules: &Rules, pending: &mut Pending, out: &mut W) -> io::Result<()> {
+    if !pending.carry.is_empty() {
+        match rules.at_end(&pending.carry, 0) {
+            Some((len, replacement)) => {
+                if replacement != &pending.carry[..len] {
+                    pending.converted += 1;
+                }
+                pending.whitespace.clear();
+                out.write_all(replacement)?;
+                out.write_all(&pending.carry[len..])?;
+            }
+            None => {
+                out.write_all(&pending.whitespace)?;
+                pending.whitespace.clear();
+                out.write_all(&pending.carry)?;
+            }
+        }
+        pending.carry.clear();
+    }
+    // whitespace at the end of the last line is trailing as well
+    pending.whitespace.clear();
+
+    Ok(())
+}
+
+/// Like [`convert_chunk`], also holding back the line breaks at the end of the output,
+/// if the end of the text is to be fixed.
+fn write_chunk<W: Write>(
+    buf: &[u8],
+    rules: &Rules,
+    pending: &mut Pending,
+    trailing: &mut Trailing,
+    out: &mut W,
+) -> io::Result<()> {
+    if trailing.mode.is_noop() {
+        convert_chunk(buf, rules, pending, out)
+    } else {
+        convert_chunk(buf, rules, pending, &mut trailing.writer(out))
+    }
+}
+
+/// Like [`finish_chunks`], also writing out the fixed end of the text.
+fn finish_writing<W: Write>(
+    rules: &Rules,
+    pending: &mut Pending,
+    trailing: &mut Trailing,
+    out: &mut W,
+) -> io::Result<()> {
+    if trailing.mode.is_noop() {
+        finish_chunks(rules, pending, out)
+    } else {
+        finish_chunks(rules, pending, &mut trailing.writer(out))?;
+        trailing.finish(out)
+    }
+}
+
+#[derive(Debug)]
+struct ConvertingWriter<W: Write> {
+    inner: Option<W>,
+    rules: Rules,
+    pending: Pending,
+    trailing: Trailing,
+}
+
+impl<W: Write> ConvertingWriter<W> {
+    fn new(inner: W, rules: Rules, final_newline: FinalNewline) -> Self {
+        ConvertingWriter {
+            inner: Some(inner),
+            rules,
+            pending: Pending::default(),
+            trailing: Trailing::new(final_newline),
+        }
+    }
+
+    fn get_ref(&self) -> &W {
+        match self.inner.as_ref() {
+            Some(w) => w,
+            None => unreachable!(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut W {
+        match self.inner.as_mut() {
+            Some(w) => w,
+            None => unreachable!(),
+        }
+    }
+
+    fn finish(self) -> io::Result<W> {
+        self.finish_counted().map(|(w, _)| w)
+    }
+
+    fn finish_counted(mut self) -> io::Result<(W, usize)> {
+        match self.inner.take() {
+            Some(mut w) => {
+                finish_writing(&self.rules, &mut self.pending, &mut self.trailing, &mut w)?;
+                w.flush()?;
+                Ok((w, self.pending.converted))
+            }
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<W: Write> Write for ConvertingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.inner.as_mut() {
+            Some(w) => write_chunk(buf, &self.rules, &mut self.pending, &mut self.trailing, w)?,
+            None => unreachable!(),
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for ConvertingWriter<W> {
+    fn drop(&mut self) {
+        if let Some(w) = self.inner.as_mut() {
+            let _ = finish_writing(&self.rules, &mut self.pending, &mut self.trailing, w);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ConvertingReader<R: Read> {
+    inner: R,
+    rules: Rules,
+    chunk: Vec<u8>,
+    buffer: Vec<u8>,
+    pos: usize,
+    pending: Pending,
+    trailing: Trailing,
+}
+
+impl<R: Read> ConvertingReader<R> {
+    fn new(inner: R, rules: Rules, final_newline: FinalNewline) -> Self {
+        ConvertingReader {
+            inner,
+            rules,
+            chunk: vec![0; CHUNK_SIZE],
+            buffer: Vec::with_capacity(2 * CHUNK_SIZE),
+            pos: 0,
+            pending: Pending::default(),
+            trailing: Trailing::new(final_newline),
+        }
+    }
+}
+
+impl<R: Read> Read for ConvertingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buffer.len() {
+            self.buffer.clear();
+            self.pos = 0;
+            let n = self.inner.read(&mut self.chunk)?;
+            if n == 0 {
+                // finishing leaves nothing held back, so it writes nothing the second time
+                let (pending, trailing) = (&mut self.pending, &mut self.trailing);
+                finish_writing(&self.rules, pending, trailing, &mut self.buffer)?;
+                if self.buffer.is_empty() {
+                    return Ok(0);
+                }
+            } else {
+                let chunk = &self.chunk[..n];
+                let (pending, trailing) = (&mut self.pending, &mut self.trailing);
+                write_chunk(chunk, &self.rules, pending, trailing, &mut self.buffer)?;
+            }
+        }
+
+        let n = (&self.buffer[self.pos..]).read(buf)?;
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+macro_rules! converting_writer {
+    ($(#[$attr:meta])* $name:ident, $rules:expr) => {
+        $(#[$attr])*
+        ///
+        /// Because a `\r` at the end of a chunk may be followed by `\n` in the next one,
+        /// it is held back until more data arrives. Call `finish` when done writing,
+        /// so it is not lost; dropping the writer does the same, but ignores any errors.
+        ///
+        /// The inner writer receives many small writes, so it is a good idea to wrap
+        /// it in [`std::io::BufWriter`] if it is not buffered already.
+        #[derive(Debug)]
+        pub struct $name<W: Write>(ConvertingWriter<W>);
+
+        impl<W: Write> $name<W> {
+            /// Creates a new converting writer, wrapping `inner`.
+            pub fn new(inner: W) -> Self {
+                $name(ConvertingWriter::new(inner, $rules, FinalNewline::default()))
+            }
+
+            /// Gets a reference to the inner writer.
+            pub fn get_ref(&self) -> &W {
+                self.0.get_ref()
+            }
+
+            /// Gets a mutable reference to the inner writer.
+            ///
+            /// Writing directly to the inner writer may corrupt the output.
+            pub fn get_mut(&mut self) -> &mut W {
+                self.0.get_mut()
+            }
+
+            /// Returns the number of line endings converted so far.
+            ///
+            /// A held back `\r` is only counted once the data following it arrives,
+            /// or when finished.
+            pub fn converted(&self) -> usize {
+                self.0.pending.converted
+            }
+
+            /// Writes out any held back `\r`, flushes and returns the inner writer.
+            pub fn finish(self) -> io::Result<W> {
+                self.0.finish()
+            }
+
+            /// Like [`finish`](Self::finish), but also returns the number of line endings converted.
+            pub fn finish_counted(self) -> io::Result<(W, usize)> {
+                self.0.finish_counted()
+            }
+        }
+
+        impl<W: Write> Write for $name<W> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            /// Flushes the inner writer.
+            ///
+            /// A held back `\r` is *not* written, as the following data may still
+            /// turn it into a `\r\n` pair.
+            fn flush(&mut self) -> io::Result<()> {
+                self.0.flush()
+            }
+        }
+    };
+}
+
+macro_rules! converting_reader {
+    ($(#[$attr:meta])* $name:ident, $rules:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<R: Read>(ConvertingReader<R>);
+
+        impl<R: Read> $name<R> {
+            /// Creates a new converting reader, wrapping `inner`.
+            pub fn new(inner: R) -> Self {
+                $name(ConvertingReader::new(inner, $rules, FinalNewline::default()))
+            }
+
+            /// Gets a reference to the inner reader.
+            pub fn get_ref(&self) -> &R {
+                &self.0.inner
+            }
+
+            /// Gets a mutable reference to the inner reader.
+            ///
+            /// Reading directly from the inner reader may corrupt the output.
+            pub fn get_mut(&mut self) -> &mut R {
+                &mut self.0.inner
+            }
+
+            /// Returns the inner reader. Any already converted, but not yet read data is lost.
+            pub fn into_inner(self) -> R {
+                self.0.inner
+            }
+
+            /// Returns the number of line endings converted so far, including the ones
+            /// converted, but not read yet.
+            pub fn converted(&self) -> usize {
+                self.0.pending.converted
+            }
+        }
+
+        impl<R: Read> Read for $name<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+    };
+}
+
+converting_writer!(
+    /// A writer converting DOS-style line endings (`\r\n`) to UNIX-style (`\n`)
+    /// before passing the data to the inner writer. See [`dos2unix`](crate::dos2unix).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Dos2UnixWriter;
+    ///
+    /// let mut writer = Dos2UnixWriter::new(Vec::new());
+    /// writer.write_all(b"foo\r")?;
+    /// writer.write_all(b"\nbar\r\n")?;
+    /// assert_eq!(writer.finish()?, b"foo\nbar\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Dos2UnixWriter,
+    DOS2UNIX
+);
+
+converting_writer!(
+    /// A writer converting UNIX-style line endings (`\n`) to DOS-style (`\r\n`)
+    /// before passing the data to the inner writer. See [`unix2dos`](crate::unix2dos).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Unix2DosWriter;
+    ///
+    /// let mut writer = Unix2DosWriter::new(Vec::new());
+    /// writer.write_all(b"foo\nbar\r")?;
+    /// writer.write_all(b"\n")?;
+    /// assert_eq!(writer.finish()?, b"foo\r\nbar\r\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Unix2DosWriter,
+    UNIX2DOS
+);
+
+converting_writer!(
+    /// A writer converting classic Mac OS line endings (`\r`) to UNIX-style (`\n`)
+    /// before passing the data to the inner writer. See [`mac2unix`](crate::mac2unix).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Mac2UnixWriter;
+    ///
+    /// let mut writer = Mac2UnixWriter::new(Vec::new());
+    /// writer.write_all(b"foo\rbar\r")?;
+    /// assert_eq!(writer.finish()?, b"foo\nbar\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Mac2UnixWriter,
+    MAC2UNIX
+);
+
+converting_writer!(
+    /// A writer converting UNIX-style line endings (`\n`) to classic Mac OS (`\r`)
+    /// before passing the data to the inner writer. See [`unix2mac`](crate::unix2mac).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Unix2MacWriter;
+    ///
+    /// let mut writer = Unix2MacWriter::new(Vec::new());
+    /// writer.write_all(b"foo\nbar\n")?;
+    /// assert_eq!(writer.finish()?, b"foo\rbar\r");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Unix2MacWriter,
+    UNIX2MAC
+);
+
+converting_writer!(
+    /// A writer converting classic Mac OS line endings (`\r`) to DOS-style (`\r\n`)
+    /// before passing the data to the inner writer. See [`mac2dos`](crate::mac2dos).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Mac2DosWriter;
+    ///
+    /// let mut writer = Mac2DosWriter::new(Vec::new());
+    /// writer.write_all(b"foo\rbar\r")?;
+    /// assert_eq!(writer.finish()?, b"foo\r\nbar\r\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Mac2DosWriter,
+    MAC2DOS
+);
+
+converting_writer!(
+    /// A writer converting DOS-style line endings (`\r\n`) to classic Mac OS (`\r`)
+    /// before passing the data to the inner writer. See [`dos2mac`](crate::dos2mac).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    /// use newline_converter::Dos2MacWriter;
+    ///
+    /// let mut writer = Dos2MacWriter::new(Vec::new());
+    /// writer.write_all(b"foo\r")?;
+    /// writer.write_all(b"\nbar\r\n")?;
+    /// assert_eq!(writer.finish()?, b"foo\rbar\r");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Dos2MacWriter,
+    DOS2MAC
+);
+
+converting_reader!(
+    /// A reader converting DOS-style line endings (`\r\n`) to UNIX-style (`\n`)
+    /// in the data read from the inner reader. See [`dos2unix`](crate::dos2unix).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Read;
+    /// use newline_converter::Dos2UnixReader;
+    ///
+    /// let mut output = String::new();
+    /// Dos2UnixReader::new(&b"foo\r\nbar\r\n"[..]).read_to_string(&mut output)?;
+    /// assert_eq!(output, "foo\nbar\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Dos2UnixReader,
+    DOS2UNIX
+);
+
+converting_reader!(
+    /// A reader converting UNIX-style line endings (`\n`) to DOS-style (`\r\n`)
+    /// in the data read from the inner reader. See [`unix2dos`](crate::unix2dos).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Read;
+    /// use newline_converter::Unix2DosReader;
+    ///
+    /// let mut output = String::new();
+    /// Unix2DosReader::new(&b"foo\nbar\r\n"[..]).read_to_string(&mut output)?;
+    /// assert_eq!(output, "foo\r\nbar\r\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    Unix2DosReader,
+    UNIX2DOS
+);
+
+converting_reader!(
+    /// A reader converting classic Mac OS line endings (`\r`) to UNIX-style (`\n`)
+    /// in the data read from the inner reader. See [`mac2unix`](crate::mac2unix).
+    Mac2UnixReader,
+    MAC2UNIX
+);
+
+converting_reader!(
+    /// A reader converting UNIX-style line endings (`\n`) to classic Mac OS (`\r`)
+    /// in the data read from the inner reader. See [`unix2mac`](crate::unix2mac).
+    Unix2MacReader,
+    UNIX2MAC
+);
+
+converting_reader!(
+    /// A reader converting classic Mac OS line endings (`\r`) to DOS-style (`\r\n`)
+    /// in the data read from the inner reader. See [`mac2dos`](crate::mac2dos).
+    Mac2DosReader,
+    MAC2DOS
+);
+
+converting_reader!(
+    /// A reader converting DOS-style line endings (`\r\n`) to classic Mac OS (`\r`)
+    /// in the data read from the inner reader. See [`dos2mac`](crate::dos2mac).
+    Dos2MacReader,
+    DOS2MAC
+);
+
+/// A writer converting all line endings to the given style before passing
+/// the data to the inner writer. See [`normalize`](crate::normalize).
+///
+/// The data is expected to be UTF-8 encoded, for Unicode line endings to be recognized.
+///
+/// Because the end of a chunk may be followed by the rest of a line ending in the
+/// next one, it may be held back until more data arrives. Call `finish` when done
+/// writing, so it is not lost; dropping the writer does the same, but ignores any errors.
+///
+/// The inner writer receives many small writes, so it is a good idea to wrap
+/// it in [`std::io::BufWriter`] if it is not buffered already.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use newline_converter::{LineEnding, NormalizeWriter};
+///
+/// let mut writer = NormalizeWriter::new(Vec::new(), LineEnding::CrLf);
+/// writer.write_all(b"foo\rbar\r")?;
+/// writer.write_all(b"\nbaz\n")?;
+/// assert_eq!(writer.finish()?, b"foo\r\nbar\r\nbaz\r\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct NormalizeWriter<W: Write>(ConvertingWriter<W>);
+
+impl<W: Write> NormalizeWriter<W> {
+    /// Creates a new converting writer, wrapping `inner`.
+    pub fn new(inner: W, target: LineEnding) -> Self {
+        let rules = Rules::normalize(target);
+        NormalizeWriter(ConvertingWriter::new(inner, rules, FinalNewline::default()))
+    }
+
+    /// Gets a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.0.get_ref()
+    }
+
+    /// Gets a mutable reference to the inner writer.
+    ///
+    /// Writing directly to the inner writer may corrupt the output.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.0.get_mut()
+    }
+
+    /// Returns the number of line endings converted so far.
+    ///
+    /// Held back data is only counted once the data following it arrives, or when finished.
+    pub fn converted(&self) -> usize {
+        self.0.pending.converted
+    }
+
+    /// Writes out any held back data, flushes and returns the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.0.finish()
+    }
+
+    /// Like [`finish`](Self::finish), but also returns the number of line endings converted.
+    pub fn finish_counted(self) -> io::Result<(W, usize)> {
+        self.0.finish_counted()
+    }
+}
+
+impl<W: Write> Write for NormalizeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    /// Flushes the inner writer.
+    ///
+    /// Held back data is *not* written, as the following data may still
+    /// change how it is converted.
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A reader converting all line endings to the given style in the data read
+/// from the inner reader. See [`normalize`](crate::normalize).
+///
+/// The data is expected to be UTF-8 encoded, for Unicode line endings to be recognized.
+///
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use newline_converter::{LineEnding, NormalizeReader};
+///
+/// let mut output = String::new();
+/// NormalizeReader::new(&b"foo\r\nbar\rbaz\n"[..], LineEnding::Lf).read_to_string(&mut output)?;
+/// assert_eq!(output, "foo\nbar\nbaz\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct NormalizeReader<R: Read>(ConvertingReader<R>);
+
+impl<R: Read> NormalizeReader<R> {
+    /// Creates a new converting reader, wrapping `inner`.
+    pub fn new(inner: R, target: LineEnding) -> Self {
+        let rules = Rules::normalize(target);
+        NormalizeReader(ConvertingReader::new(inner, rules, FinalNewline::default()))
+    }
+
+    /// Gets a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.0.inner
+    }
+
+    /// Gets a mutable reference to the inner reader.
+    ///
+    /// Reading directly from the inner reader may corrupt the output.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.0.inner
+    }
+
+    /// Returns the inner reader. Any already converted, but not yet read data is lost.
+    pub fn into_inner(self) -> R {
+        self.0.inner
+    }
+
+    /// Returns the number of line endings converted so far, including the ones
+    /// converted, but not read yet.
+    pub fn converted(&self) -> usize {
+        self.0.pending.converted
+    }
+}
+
+impl<R: Read> Read for NormalizeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// A writer fixing the line breaks at the end of the data written to it, like
+/// [`add_final_newline`](crate::add_final_newline) and
+/// [`trim_trailing_newlines`](crate::trim_trailing_newlines) do in memory.
+///
+/// Line breaks are held back until some other data follows them, so they are known
+/// to be trailing when finished. Call [`finish`] when done writing, so they are not lost;
+/// dropping the writer does the same, but ignores any errors.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use newline_converter::{FinalNewlineWriter, LineEnding};
+///
+/// let mut writer = FinalNewlineWriter::new(Vec::new(), Some(LineEnding::Lf), true);
+/// writer.write_all(b"foo\n\n")?;
+/// writer.write_all(b"bar\n\n\n")?;
+/// assert_eq!(writer.finish()?, b"foo\n\nbar\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`finish`]: FinalNewlineWriter::finish
+#[derive(Debug)]
+pub struct FinalNewlineWriter<W: Write> {
+    inner: Option<W>,
+    trailing: Trailing,
+}
+
+impl<W: Write> FinalNewlineWriter<W> {
+    /// Creates a new writer, wrapping `inner`. A line ending in the `add` style is added
+    /// at the end if it is missing, and blank lines at the end are removed if `trim` is set.
+    /// NEL, LS and PS are line breaks only if `add` is one of these.
+    pub fn new(inner: W, add: Option<LineEnding>, trim: bool) -> Self {
+        let unicode = matches!(add, Some(ending) if ending.is_unicode());
+        FinalNewlineWriter {
+            inner: Some(inner),
+            trailing: Trailing::new(FinalNewline { add, trim, unicode }),
+        }
+    }
+
+    /// Gets a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        match self.inner.as_ref() {
+            Some(w) => w,
+            None => unreachable!(),
+        }
+    }
+
+    /// Gets a mutable reference to the inner writer.
+    ///
+    /// Writing directly to the inner writer may corrupt the output.
+    pub fn get_mut(&mut self) -> &mut W {
+        match self.inner.as_mut() {
+            Some(w) => w,
+            None => unreachable!(),
+        }
+    }
+
+    /// Writes out the end of the data, flushes and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.inner.take() {
+            Some(mut w) => {
+                self.trailing.finish(&mut w)?;
+                w.flush()?;
+                Ok(w)
+            }
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<W: Write> Write for FinalNewlineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.inner.as_mut() {
+            Some(w) => self.trailing.writer(w).write(buf),
+            None => unreachable!(),
+        }
+    }
+
+    /// Flushes the inner writer.
+    ///
+    /// Held back line breaks are *not* written, as the following data may still
+    /// tell whether they are trailing.
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for FinalNewlineWriter<W> {
+    fn drop(&mut self) {
+        if let Some(w) = self.inner.as_mut() {
+            let _ = self.trailing.finish(w);
+        }
+    }
+}
+
+/// A writer converting the data written to it, and writing it to the inner writer,
+/// created by [`Converter::writer`](crate::Converter::writer).
+///
+/// A possible beginning of a line ending at the end of a chunk, and whitespace which
+/// may turn out to be trailing, are held back until more data arrives. Call [`finish`]
+/// when done writing, so they are not lost; dropping the writer does the same, but ignores
+/// any errors.
+///
+/// The inner writer receives many small writes, so it is a good idea to wrap
+/// it in [`std::io::BufWriter`] if it is not buffered already.
+///
+/// [`finish`]: ConverterWriter::finish
+#[derive(Debug)]
+pub struct ConverterWriter<W: Write>(ConvertingWriter<W>);
+
+impl<W: Write> ConverterWriter<W> {
+    pub(crate) fn new(inner: W, rules: Rules, final_newline: FinalNewline) -> Self {
+        ConverterWriter(ConvertingWriter::new(inner, rules, final_newline))
+    }
+
+    /// Gets a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.0.get_ref()
+    }
+
+    /// Gets a mutable reference to the inner writer.
+    ///
+    /// Writing directly to the inner writer may corrupt the output.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.0.get_mut()
+    }
+
+    /// Returns the number of line endings converted so far.
+    ///
+    /// Held back data is only counted once the data following it arrives, or when finished.
+    pub fn converted(&self) -> usize {
+        self.0.pending.converted
+    }
+
+    /// Writes out any held back data, flushes and returns the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.0.finish()
+    }
+
+    /// Like [`finish`](Self::finish), but also returns the number of line endings converted.
+    pub fn finish_counted(self) -> io::Result<(W, usize)> {
+        self.0.finish_counted()
+    }
+}
+
+impl<W: Write> Write for ConverterWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    /// Flushes the inner writer.
+    ///
+    /// Held back data is *not* written, as the following data may still
+    /// change how it is converted.
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A reader converting the data read from the inner reader,
+/// created by [`Converter::reader`](crate::Converter::reader).
+#[derive(Debug)]
+pub struct ConverterReader<R: Read>(ConvertingReader<R>);
+
+impl<R: Read> ConverterReader<R> {
+    pub(crate) fn new(inner: R, rules: Rules, final_newline: FinalNewline) -> Self {
+        ConverterReader(ConvertingReader::new(inner, rules, final_newline))
+    }
+
+    /// Gets a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.0.inner
+    }
+
+    /// Gets a mutable reference to the inner reader.
+    ///
+    /// Reading directly from the inner reader may corrupt the output.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.0.inner
+    }
+
+    /// Returns the inner reader. Any already converted, but not yet read data is lost.
+    pub fn into_inner(self) -> R {
+        self.0.inner
+    }
+
+    /// Returns the number of line endings converted so far, including the ones
+    /// converted, but not read yet.
+    pub fn converted(&self) -> usize {
+        self.0.pending.converted
+    }
+}
+
+impl<R: Read> Read for ConverterReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        add_final_newline, analyze, dos2mac, dos2unix, dos2unix_bytes, dos2unix_counted, mac2dos,
+        mac2unix, normalize, trim_trailing_newlines, unix2dos_bytes, unix2dos_counted, unix2mac,
+        Converter,
+    };
+    use quickcheck::quickcheck;
+    use std::borrow::Cow;
+
+    /// Writes `data` in chunks of given sizes (cycling through them).
+    fn write_chunked<W: Write>(writer: &mut W, data: &[u8], sizes: &[usize]) {
+        let mut rest = data;
+        let mut sizes = sizes.iter().map(|x| x % 16 + 1).cycle();
+        while !rest.is_empty() {
+            let n = sizes.next().unwrap_or(1).min(rest.len());
+            writer.write_all(&rest[..n]).unwrap();
+            rest = &rest[n..];
+        }
+    }
+
+    /// A reader returning at most `n` bytes per call.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn dos2unix_writer_split_crlf() {
+        let mut writer = Dos2UnixWriter::new(Vec::new());
+        writer.write_all(b"foo\r").unwrap();
+        writer.write_all(b"\nbar\r").unwrap();
+        writer.write_all(b"baz\r").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\nbar\rbaz\r");
+    }
+
+    #[test]
+    fn dos2unix_writer_drop_writes_pending() {
+        let mut output = Vec::new();
+        {
+            let mut writer = Dos2UnixWriter::new(&mut output);
+            writer.write_all(b"foo\r").unwrap();
+        }
+        assert_eq!(output, b"foo\r");
+    }
+
+    #[test]
+    fn unix2dos_writer_split_crlf() {
+        let mut writer = Unix2DosWriter::new(Vec::new());
+        writer.write_all(b"foo\r").unwrap();
+        writer.write_all(b"\nbar").unwrap();
+        writer.write_all(b"\n").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\r\nbar\r\n");
+    }
+
+    #[test]
+    fn normalize_writer_split_unicode() {
+        let mut writer = NormalizeWriter::new(Vec::new(), LineEnding::Lf);
+        writer.write_all(b"foo\xe2").unwrap();
+        writer.write_all(b"\x80").unwrap();
+        writer.write_all(b"\xa8bar\xc2").unwrap();
+        writer.write_all(b"\x85baz\xe2\x80").unwrap();
+        writer.write_all(b"\x99\xc2").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\nbar\nbaz\xe2\x80\x99\xc2");
+    }
+
+    #[test]
+    fn converter_writer_split_whitespace() {
+        let mut writer = Converter::unix2dos()
+            .strip_trailing_whitespace(true)
+            .writer(Vec::new());
+        writer.write_all(b"foo \t").unwrap();
+        writer.write_all(b" \r").unwrap();
+        writer.write_all(b"\nbar ").unwrap();
+        writer.write_all(b" baz\t").unwrap();
+        writer.write_all(b"\n \t").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\r\nbar  baz\r\n");
+    }
+
+    #[test]
+    fn converter_writer_split_trailing_newlines() {
+        let mut writer = Converter::dos2unix()
+            .trim_trailing_newlines(true)
+            .writer(Vec::new());
+        writer.write_all(b"foo\r").unwrap();
+        writer.write_all(b"\n\r\n").unwrap();
+        writer.write_all(b"bar\r\n\r").unwrap();
+        writer.write_all(b"\n\n").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\n\nbar\n");
+    }
+
+    #[test]
+    fn dos2unix_reader_split_crlf() {
+        let mut output = Vec::new();
+        Dos2UnixReader::new(Trickle(b"foo\r\nbar\r", 4))
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(output, b"foo\nbar\r");
+    }
+
+    #[test]
+    fn unix2dos_reader_split_crlf() {
+        let mut output = Vec::new();
+        Unix2DosReader::new(Trickle(b"foo\r\nbar\n", 4))
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(output, b"foo\r\nbar\r\n");
+    }
+
+    #[test]
+    fn final_newline_writer_split_trailing_newlines() {
+        let mut writer = FinalNewlineWriter::new(Vec::new(), None, true);
+        writer.write_all(b"foo\r").unwrap();
+        writer.write_all(b"\n\r\n").unwrap();
+        writer.write_all(b"bar\r\n\r").unwrap();
+        writer.write_all(b"\n\n").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\r\n\r\nbar\r\n");
+    }
+
+    #[test]
+    fn final_newline_writer_split_unicode() {
+        let mut writer = FinalNewlineWriter::new(Vec::new(), Some(LineEnding::Ls), true);
+        writer.write_all(b"foo\xe2").unwrap();
+        writer.write_all(b"\x80").unwrap();
+        writer.write_all(b"\xa8\xe2\x80\xa8\xc2").unwrap();
+        writer.write_all(b"\x85").unwrap();
+        assert_eq!(writer.finish().unwrap(), "foo\u{2028}".as_bytes());
+
+        // not a line break after all
+        let mut writer = FinalNewlineWriter::new(Vec::new(), Some(LineEnding::Ls), true);
+        writer.write_all(b"foo\n\xe2\x80").unwrap();
+        writer.write_all(b"\x94").unwrap();
+        assert_eq!(writer.finish().unwrap(), "foo\n\u{2014}\u{2028}".as_bytes());
+        let mut writer = FinalNewlineWriter::new(Vec::new(), Some(LineEnding::Ls), true);
+        writer.write_all(b"foo\n\xc2").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"foo\n\xc2\xe2\x80\xa8");
+    }
+
+    quickcheck! {
+        fn trailing_equals_in_memory(
+            pieces: Vec<u8>,
+            sizes: Vec<usize>,
+            add: bool,
+            trim: bool
+        ) -> bool {
+            // line breaks, and what looks like their beginnings
+            let alphabet: [&[u8]; 8] = [
+                b"a",
+                b"\n",
+                b"\r",
+                "\u{85}".as_bytes(),
+                "\u{2028}".as_bytes(),
+                "\u{2029}".as_bytes(),
+                "\u{2014}".as_bytes(),
+                b"\xc2",
+            ];
+            let data: Vec<u8> = pieces
+                .iter()
+                .flat_map(|&p| alphabet[p as usize % alphabet.len()].iter().copied())
+                .collect();
+            let mode = FinalNewline {
+                add: if add { Some(LineEnding::Ps) } else { None },
+                trim,
+                unicode: true,
+            };
+
+            let mut trailing = Trailing::new(mode);
+            let mut output = Vec::new();
+            write_chunked(&mut trailing.writer(&mut output), &data, &sizes);
+            trailing.finish(&mut output).unwrap();
+            output == mode.apply_bytes(Cow::Borrowed(&data)).as_ref()
+        }
+
+        fn final_newline_writer_equals_functions(
+            data: String,
+            sizes: Vec<usize>,
+            add: bool,
+            trim: bool
+        ) -> bool {
+            let add = if add { Some(LineEnding::CrLf) } else { None };
+            let mut writer = FinalNewlineWriter::new(Vec::new(), add, trim);
+            write_chunked(&mut writer, data.as_bytes(), &sizes);
+            let mut expected = data.clone();
+            if trim {
+                expected = trim_trailing_newlines(&expected).into_owned();
+            }
+            if let Some(ending) = add {
+                expected = add_final_newline(&expected, ending).into_owned();
+            }
+            writer.finish().unwrap() == expected.as_bytes()
+        }
+
+        fn dos2unix_writer_equals_dos2unix_bytes(data: Vec<u8>, sizes: Vec<usize>) -> bool {
+            let mut writer = Dos2UnixWriter::new(Vec::new());
+            write_chunked(&mut writer, &data, &sizes);
+            writer.finish().unwrap() == dos2unix_bytes(&data).as_ref()
+        }
+
+        fn unix2dos_writer_equals_unix2dos_bytes(data: Vec<u8>, sizes: Vec<usize>) -> bool {
+            let mut writer = Unix2DosWriter::new(Vec::new());
+            write_chunked(&mut writer, &data, &sizes);
+            writer.finish().unwrap() == unix2dos_bytes(&data).as_ref()
+        }
+
+        fn mac_writers_equal_mac_functions(data: String, sizes: Vec<usize>) -> bool {
+            let mut mac2unix_writer = Mac2UnixWriter::new(Vec::new());
+            write_chunked(&mut mac2unix_writer, data.as_bytes(), &sizes);
+            let mut unix2mac_writer = Unix2MacWriter::new(Vec::new());
+            write_chunked(&mut unix2mac_writer, data.as_bytes(), &sizes);
+            let mut mac2dos_writer = Mac2DosWriter::new(Vec::new());
+            write_chunked(&mut mac2dos_writer, data.as_bytes(), &sizes);
+            let mut dos2mac_writer = Dos2MacWriter::new(Vec::new());
+            write_chunked(&mut dos2mac_writer, data.as_bytes(), &sizes);
+
+            mac2unix_writer.finish().unwrap() == mac2unix(&data).as_bytes()
+                && unix2mac_writer.finish().unwrap() == unix2mac(&data).as_bytes()
+                && mac2dos_writer.finish().unwrap() == mac2dos(&data).as_bytes()
+                && dos2mac_writer.finish().unwrap() == dos2mac(&data).as_bytes()
+        }
+
+        fn normalize_writer_equals_normalize(data: String, sizes: Vec<usize>) -> bool {
+            [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr, LineEnding::Ls]
+                .iter()
+                .all(|&target| {
+                    let mut writer = NormalizeWriter::new(Vec::new(), target);
+                    write_chunked(&mut writer, data.as_bytes(), &sizes);
+                    writer.finish().unwrap() == normalize(&data, target).as_bytes()
+                })
+        }
+
+        fn writers_count_like_counted_functions(data: String, sizes: Vec<usize>) -> bool {
+            let mut dos2unix_writer = Dos2UnixWriter::new(Vec::new());
+            write_chunked(&mut dos2unix_writer, data.as_bytes(), &sizes);
+            let mut unix2dos_writer = Unix2DosWriter::new(Vec::new());
+            write_chunked(&mut unix2dos_writer, data.as_bytes(), &sizes);
+
+            dos2unix_writer.finish_counted().unwrap().1 == dos2unix_counted(&data).converted
+                && unix2dos_writer.finish_counted().unwrap().1
+                    == unix2dos_counted(&data).converted
+        }
+
+        fn mac2unix_writer_counts_lone_crs(data: String, sizes: Vec<usize>) -> bool {
+            let mut writer = Mac2UnixWriter::new(Vec::new());
+            write_chunked(&mut writer, data.as_bytes(), &sizes);
+            writer.finish_counted().unwrap().1 == analyze(&data).cr
+        }
+
+        fn converter_writer_equals_converter(data: Vec<u8>, sizes: Vec<usize>) -> bool {
+            [
+                Converter::dos2unix(),
+                Converter::mac2unix(),
+                Converter::normalize(LineEnding::CrLf),
+            ]
+            .iter()
+            .map(|converter| converter.strip_trailing_whitespace(true))
+            .all(|converter| {
+                let mut writer = converter.writer(Vec::new());
+                write_chunked(&mut writer, &data, &sizes);
+                writer.finish().unwrap() == converter.convert_bytes(&data).as_ref()
+            })
+        }
+
+        fn converter_reader_equals_converter(data: Vec<u8>, size: usize) -> bool {
+            let converter = Converter::normalize(LineEnding::Lf).strip_trailing_whitespace(true);
+            let mut output = Vec::new();
+            converter
+                .reader(Trickle(&data, size % 16 + 1))
+                .read_to_end(&mut output)
+                .unwrap();
+            output == converter.convert_bytes(&data).as_ref()
+        }
+
+        fn converter_final_newline_equals_functions(data: String, add: bool, trim: bool) -> bool {
+            let converter = Converter::dos2unix()
+                .add_final_newline(add)
+                .trim_trailing_newlines(trim);
+            let mut expected = dos2unix(&data);
+            if trim {
+                expected = trim_trailing_newlines(&expected).into_owned().into();
+            }
+            if add {
+                expected = add_final_newline(&expected, LineEnding::Lf).into_owned().into();
+            }
+            converter.convert(&data) == expected
+        }
+
+        fn converter_writer_fixes_end_like_converter(
+            data: Vec<u8>,
+            sizes: Vec<usize>,
+            add: bool,
+            trim: bool
+        ) -> bool {
+            [
+                Converter::unix2dos(),
+                Converter::mac2unix().strip_trailing_whitespace(true),
+                Converter::normalize(LineEnding::Nel),
+            ]
+                .iter()
+                .map(|converter| converter.add_final_newline(add).trim_trailing_newlines(trim))
+                .all(|converter| {
+                    let mut writer = converter.writer(Vec::new());
+                    write_chunked(&mut writer, &data, &sizes);
+                    writer.finish().unwrap() == converter.convert_bytes(&data).as_ref()
+                })
+        }
+
+        fn converter_reader_fixes_end_like_converter(data: Vec<u8>, size: usize) -> bool {
+            [LineEnding::CrLf, LineEnding::Ls].iter().all(|&target| {
+                let converter = Converter::normalize(target)
+                    .add_final_newline(true)
+                    .trim_trailing_newlines(true);
+                let mut output = Vec::new();
+                converter
+                    .reader(Trickle(&data, size % 16 + 1))
+                    .read_to_end(&mut output)
+                    .unwrap();
+                output == converter.convert_bytes(&data).as_ref()
+            })
+        }
+
+        fn dos2unix_reader_equals_dos2unix_bytes(data: Vec<u8>, size: usize) -> bool {
+            let mut output = Vec::new();
+            Dos2UnixReader::new(Trickle(&data, size % 16 + 1))
+                .read_to_end(&mut output)
+                .unwrap();
+            output == dos2unix_bytes(&data).as_ref()
+        }
+
+        fn unix2dos_reader_equals_unix2dos_bytes(data: Vec<u8>, size: usize) -> bool {
+            let mut output = Vec::new();
+            Unix2DosReader::new(Trickle(&data, size % 16 + 1))
+                .read_to_end(&mut output)
+                .unwrap();
+            output == unix2dos_bytes(&data).as_ref()
+        }
+    }
+}